@@ -1,26 +1,258 @@
 use dotenv::dotenv;
+use std::fmt::Write;
+use std::path::Path;
+
+// connection settings the firmware can't do without
+const REQUIRED_VARS: &[&str] = &["WIFI_PASS", "WIFI_SSID", "SERVER_ADDR"];
+// settings the firmware has defaults for, they are only passed on when set in .env
+const OPTIONAL_VARS: &[&str] = &[
+    "STALE_AFTER_SECS",
+    "NTP_SERVERS",
+    "TIMEZONE",
+    "CLOCK_FORMAT",
+    "LANGUAGE",
+    "HISTORY_RESOLUTION_SECS",
+    "PERSIST_INTERVAL_SECS",
+    "ENERGY_DEVIATION_PERCENT",
+    "TARIFF_IMPORT",
+    "TARIFF_FEED_IN",
+    "TARIFF_BANDS",
+    "CURRENCY",
+    "CO2_FACTOR",
+    "BATTERY_CAPACITY_KWH",
+    "DASHBOARD_PAGE_SECS",
+    "CHART_PAGE_SECS",
+    "CHART_HOURS",
+    "FORECAST_CHART_HOURS",
+    "RECORDS_PAGE_SECS",
+    "PERIODS_PAGE_SECS",
+    "LOW_BATTERY_PERCENT",
+    "REFRESH_MAX_QUICK",
+    "REFRESH_MAX_AREA",
+    "REFRESH_MIN_INTERVAL_SECS",
+    "REFRESH_SLOW_INTERVAL_SECS",
+    "QUIET_HOURS",
+];
+
 fn main() {
     // read the env vars from .env and set them as rustc env vars so the compiler can read them
     // and include them on compile time
 
     dotenv().ok();
+    // only the firmware needs the connection settings, the library tests run on the host
+    let firmware = std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("espidf");
     match std::env::var("WIFI_PASS") {
+        Err(_) if !firmware => (),
         Err(_) => panic!("Error Wifi Pass not set! Please add WIFI_PASS to .env"),
         Ok(pass) => {
             println!("cargo:rustc-env=WIFI_PASS={}", pass);
         }
     }
     match std::env::var("WIFI_SSID") {
+        Err(_) if !firmware => (),
         Err(_) => panic!("Error Wifi SSID not set! Please add WIFI_SSID to .env"),
         Ok(ssid) => {
             println!("cargo:rustc-env=WIFI_SSID={}", ssid);
         }
     }
     match std::env::var("SERVER_ADDR") {
+        Err(_) if !firmware => (),
         Err(_) => panic!("Error SERVER_ADDR not set! Please add SERVER_ADDR to .env"),
         Ok(addr) => {
             println!("cargo:rustc-env=SERVER_ADDR={}", addr);
         }
     }
-    embuild::espidf::sysenv::output();
+    for key in OPTIONAL_VARS {
+        if let Ok(value) = std::env::var(key) {
+            println!("cargo:rustc-env={}={}", key, value);
+        }
+    }
+    // edited or added icons have to regenerate the constants, listing a path turns off the
+    // rerun on every change, so .env and this file are listed as well. A listed path that
+    // doesn't exist would rerun the script on every build
+    println!("cargo:rerun-if-changed=assets");
+    if Path::new(".env").exists() {
+        println!("cargo:rerun-if-changed=.env");
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    // the settings can also come from the environment instead of .env
+    for key in REQUIRED_VARS.iter().chain(OPTIONAL_VARS) {
+        println!("cargo:rerun-if-env-changed={}", key);
+    }
+    generate_icons(Path::new("assets"));
+    if firmware {
+        embuild::espidf::sysenv::output();
+    }
+}
+
+// every .pbm or .png file in assets becomes a constant of the icons module, named after the
+// file: assets/lightning_bolt.pbm is icons::LIGHTNING_BOLT
+fn generate_icons(dir: &Path) {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("Error reading {}: {}", dir.display(), err))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| matches!(extension(path).as_deref(), Some("pbm" | "png")))
+        .collect();
+    // the same order on every build
+    paths.sort();
+    let mut out = String::new();
+    for path in paths {
+        let (width, height, pixels) = match extension(&path).as_deref() {
+            Some("png") => read_png(&path),
+            _ => read_pbm(&path),
+        }
+        .unwrap_or_else(|err| panic!("Error in icon {}: {}", path.display(), err));
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_uppercase()
+            .replace(['-', ' '], "_");
+        let bytes: Vec<String> = pack(width, height, &pixels)
+            .iter()
+            .map(|byte| format!("0x{:02x}", byte))
+            .collect();
+        writeln!(
+            out,
+            "pub static {}: Bitmap = Bitmap::new({}, &[{}]);",
+            name,
+            width,
+            bytes.join(", ")
+        )
+        .unwrap();
+    }
+    let target = Path::new(&std::env::var("OUT_DIR").unwrap()).join("icons.rs");
+    std::fs::write(&target, out)
+        .unwrap_or_else(|err| panic!("Error writing {}: {}", target.display(), err));
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+}
+
+// rows start on a new byte, the highest bit is the leftmost pixel
+fn pack(width: usize, height: usize, pixels: &[bool]) -> Vec<u8> {
+    let row = width.div_ceil(8);
+    let mut bytes = vec![0u8; row * height];
+    for (idx, _) in pixels.iter().enumerate().filter(|(_, set)| **set) {
+        let (x, y) = (idx % width, idx / width);
+        bytes[y * row + x / 8] |= 0x80 >> (x % 8);
+    }
+    bytes
+}
+
+// plain (P1) and raw (P4) bitmaps, 1 is a set pixel
+fn read_pbm(path: &Path) -> Result<(usize, usize, Vec<bool>), String> {
+    let data = std::fs::read(path).map_err(|err| err.to_string())?;
+    let mut pos = 0;
+    // the header is the magic number, width and height, comments run to the end of the line
+    let mut token = || -> Result<String, String> {
+        loop {
+            match data.get(pos) {
+                Some(b'#') => {
+                    while data.get(pos).is_some_and(|byte| *byte != b'\n') {
+                        pos += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => pos += 1,
+                Some(_) => break,
+                None => return Err("header ends early".to_string()),
+            }
+        }
+        let start = pos;
+        while data
+            .get(pos)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            pos += 1;
+        }
+        Ok(String::from_utf8_lossy(&data[start..pos]).into_owned())
+    };
+    let magic = token()?;
+    let mut number = || -> Result<usize, String> {
+        let value = token()?;
+        value.parse().map_err(|_| format!("invalid size {}", value))
+    };
+    let (width, height) = (number()?, number()?);
+    let pixels: Vec<bool> = match magic.as_str() {
+        "P1" => {
+            let mut comment = false;
+            data[pos..]
+                .iter()
+                .filter(|byte| {
+                    comment = match **byte {
+                        b'#' => true,
+                        b'\n' => false,
+                        _ => comment,
+                    };
+                    !comment && matches!(byte, b'0' | b'1')
+                })
+                .map(|byte| *byte == b'1')
+                .take(width * height)
+                .collect()
+        }
+        // a single whitespace separates the header from the packed rows
+        "P4" => {
+            let row = width.div_ceil(8);
+            let rows = data.get(pos + 1..).unwrap_or_default();
+            if rows.len() < row * height {
+                return Err("image data ends early".to_string());
+            }
+            (0..width * height)
+                .map(|idx| {
+                    let (x, y) = (idx % width, idx / width);
+                    rows[y * row + x / 8] & (0x80 >> (x % 8)) != 0
+                })
+                .collect()
+        }
+        _ => return Err(format!("unsupported format {}, only P1 and P4 are", magic)),
+    };
+    if pixels.len() < width * height {
+        return Err("image data ends early".to_string());
+    }
+    Ok((width, height, pixels))
+}
+
+// dark opaque pixels are set, everything else stays clear
+fn read_png(path: &Path) -> Result<(usize, usize, Vec<bool>), String> {
+    let file = std::fs::File::open(path).map_err(|err| err.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        // palettes are expanded to rgb
+        png::ColorType::Indexed => return Err("palette was not expanded".to_string()),
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+    let pixels = (0..width * height)
+        .map(|idx| {
+            let (x, y) = (idx % width, idx / width);
+            let pixel = &buffer[y * info.line_size + x * channels..][..channels];
+            let (luma, alpha) = match pixel {
+                [gray] => (*gray as u32, 255),
+                [gray, alpha] => (*gray as u32, *alpha),
+                [r, g, b] => (
+                    (*r as u32 * 299 + *g as u32 * 587 + *b as u32 * 114) / 1000,
+                    255,
+                ),
+                [r, g, b, alpha] => (
+                    (*r as u32 * 299 + *g as u32 * 587 + *b as u32 * 114) / 1000,
+                    *alpha,
+                ),
+                _ => (255, 0),
+            };
+            luma < 128 && alpha >= 128
+        })
+        .collect();
+    Ok((width, height, pixels))
 }
@@ -0,0 +1,260 @@
+use std::time::{Duration, Instant};
+
+use crate::state::Language;
+
+// the battery power jumps with every kettle, the estimate follows it this slowly
+const SMOOTHING: Duration = Duration::from_secs(5 * 60);
+// below this the battery is idle and any estimate would be days away
+const MIN_POWER_KW: f32 = 0.05;
+// longer estimates are not shown, the power will change long before
+const MAX_ESTIMATE: Duration = Duration::from_secs(48 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Estimate {
+    Idle,
+    Full,
+    Empty,
+    FullIn(Duration),
+    EmptyIn(Duration),
+}
+
+// estimates the time until the battery is full or empty from the smoothed battery power
+pub struct BatteryEstimator {
+    // usable capacity in kWh
+    capacity: f32,
+    // kW, negative while discharging
+    smoothed: Option<f32>,
+    last: Option<Instant>,
+}
+
+impl BatteryEstimator {
+    pub fn new(capacity: f32) -> Self {
+        BatteryEstimator {
+            capacity,
+            smoothed: None,
+            last: None,
+        }
+    }
+    // exponential moving average, so irregular message intervals are weighted by time
+    pub fn update(&mut self, battery: f32, now: Instant) -> f32 {
+        let smoothed = match (self.smoothed, self.last) {
+            (Some(smoothed), Some(last)) => {
+                let elapsed = now.duration_since(last).as_secs_f32();
+                let alpha = 1.0 - (-elapsed / SMOOTHING.as_secs_f32()).exp();
+                smoothed + alpha * (battery - smoothed)
+            }
+            _ => battery,
+        };
+        self.smoothed = Some(smoothed);
+        self.last = Some(now);
+        smoothed
+    }
+    // soc in percent
+    pub fn estimate(&self, soc: f32) -> Estimate {
+        let power = match self.smoothed {
+            Some(power) if power.abs() >= MIN_POWER_KW => power,
+            _ => return Estimate::Idle,
+        };
+        let energy = match power > 0.0 {
+            true if soc >= 99.5 => return Estimate::Full,
+            false if soc <= 0.5 => return Estimate::Empty,
+            true => self.capacity * (100.0 - soc) / 100.0,
+            false => self.capacity * soc / 100.0,
+        };
+        let duration = Duration::from_secs_f32((energy / power.abs() * 3600.0).max(0.0));
+        match (duration > MAX_ESTIMATE, power > 0.0) {
+            (true, _) => Estimate::Idle,
+            (false, true) => Estimate::FullIn(duration),
+            (false, false) => Estimate::EmptyIn(duration),
+        }
+    }
+}
+
+// "1h40" or "25min"
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = (duration.as_secs() + 30) / 60;
+    match minutes < 60 {
+        true => format!("{}min", minutes),
+        false => format!("{}h{:02}", minutes / 60, minutes % 60),
+    }
+}
+
+// the two lines under the battery, the empty time is shown as a time of day when the clock
+// is known. None when there is nothing worth showing
+pub fn lines(
+    estimate: Estimate,
+    empty_at: Option<String>,
+    language: Language,
+) -> Option<(&'static str, String)> {
+    let (full, full_in, empty, empty_at_label, empty_in) = match language {
+        Language::En => ("full", "full in", "empty", "empty at", "empty in"),
+        Language::De => ("voll", "voll in", "leer", "leer um", "leer in"),
+    };
+    match estimate {
+        Estimate::Idle => None,
+        Estimate::Full => Some((full, String::new())),
+        Estimate::Empty => Some((empty, String::new())),
+        Estimate::FullIn(duration) => Some((full_in, format_duration(duration))),
+        Estimate::EmptyIn(duration) => match empty_at {
+            Some(time) => Some((empty_at_label, time)),
+            None => Some((empty_in, format_duration(duration))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimator(battery: f32) -> BatteryEstimator {
+        let mut estimator = BatteryEstimator::new(10.0);
+        estimator.update(battery, Instant::now());
+        estimator
+    }
+
+    #[test]
+    fn idle_below_the_minimum_power() {
+        assert_eq!(estimator(0.0).estimate(50.0), Estimate::Idle);
+        assert_eq!(estimator(0.049).estimate(50.0), Estimate::Idle);
+        assert_eq!(estimator(-0.049).estimate(50.0), Estimate::Idle);
+        assert_eq!(BatteryEstimator::new(10.0).estimate(50.0), Estimate::Idle);
+        // the minimum itself already counts
+        assert!(matches!(
+            estimator(0.05).estimate(90.0),
+            Estimate::FullIn(_)
+        ));
+        assert!(matches!(
+            estimator(-0.05).estimate(5.0),
+            Estimate::EmptyIn(_)
+        ));
+    }
+
+    #[test]
+    fn time_until_full_or_empty() {
+        // 5 kWh at 2.5 kW
+        assert_eq!(
+            estimator(2.5).estimate(50.0),
+            Estimate::FullIn(Duration::from_secs(2 * 3600))
+        );
+        // 2 kWh at 1 kW
+        assert_eq!(
+            estimator(-1.0).estimate(20.0),
+            Estimate::EmptyIn(Duration::from_secs(2 * 3600))
+        );
+    }
+
+    #[test]
+    fn estimates_beyond_48h_are_not_shown() {
+        // 5 kWh at 0.1 kW takes 50h
+        assert_eq!(estimator(0.1).estimate(50.0), Estimate::Idle);
+        // 4.7 kWh at 0.1 kW takes 47h
+        match estimator(0.1).estimate(53.0) {
+            Estimate::FullIn(duration) => assert_eq!(format_duration(duration), "47h00"),
+            estimate => panic!("{:?}", estimate),
+        }
+    }
+
+    #[test]
+    fn full_and_empty_at_the_ends() {
+        assert_eq!(estimator(2.0).estimate(100.0), Estimate::Full);
+        assert_eq!(estimator(2.0).estimate(99.6), Estimate::Full);
+        assert_eq!(estimator(-2.0).estimate(0.0), Estimate::Empty);
+        // discharging a full battery and charging an empty one
+        assert_eq!(
+            estimator(-2.0).estimate(100.0),
+            Estimate::EmptyIn(Duration::from_secs(5 * 3600))
+        );
+        assert_eq!(
+            estimator(2.0).estimate(0.0),
+            Estimate::FullIn(Duration::from_secs(5 * 3600))
+        );
+    }
+
+    #[test]
+    fn the_average_follows_slowly_and_restarts_after_a_long_gap() {
+        let start = Instant::now();
+        let mut estimator = BatteryEstimator::new(10.0);
+        assert_eq!(estimator.update(2.0, start), 2.0);
+        // one time constant later it moved 63% of the way
+        let smoothed = estimator.update(-2.0, start + SMOOTHING);
+        assert!(
+            (smoothed - (2.0 - 4.0 * 0.632)).abs() < 0.01,
+            "{}",
+            smoothed
+        );
+        // after hours without messages the old power no longer counts
+        let smoothed = estimator.update(-2.0, start + SMOOTHING * 100);
+        assert!((smoothed + 2.0).abs() < 0.001, "{}", smoothed);
+    }
+
+    #[test]
+    fn sign_flips_pass_through_idle() {
+        let start = Instant::now();
+        let mut estimator = BatteryEstimator::new(10.0);
+        estimator.update(1.0, start);
+        assert!(matches!(estimator.estimate(50.0), Estimate::FullIn(_)));
+        // the kettle turns on, the charge becomes a discharge
+        let mut now = start;
+        let mut seen = Vec::new();
+        for _ in 0..30 {
+            now += Duration::from_secs(60);
+            estimator.update(-1.0, now);
+            seen.push(std::mem::discriminant(&estimator.estimate(50.0)));
+        }
+        seen.dedup();
+        let expected: Vec<_> = [
+            Estimate::FullIn(Duration::ZERO),
+            Estimate::Idle,
+            Estimate::EmptyIn(Duration::ZERO),
+        ]
+        .iter()
+        .map(std::mem::discriminant)
+        .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn lines_depend_on_the_estimate() {
+        let hours = Duration::from_secs(100 * 60);
+        assert_eq!(lines(Estimate::Idle, None, Language::En), None);
+        assert_eq!(
+            lines(Estimate::Full, None, Language::En),
+            Some(("full", String::new()))
+        );
+        assert_eq!(
+            lines(Estimate::Empty, None, Language::De),
+            Some(("leer", String::new()))
+        );
+        assert_eq!(
+            lines(
+                Estimate::FullIn(hours),
+                Some("18:00".to_string()),
+                Language::En
+            ),
+            Some(("full in", "1h40".to_string()))
+        );
+        assert_eq!(
+            lines(
+                Estimate::EmptyIn(hours),
+                Some("18:00".to_string()),
+                Language::En
+            ),
+            Some(("empty at", "18:00".to_string()))
+        );
+        assert_eq!(
+            lines(Estimate::EmptyIn(hours), None, Language::De),
+            Some(("leer in", "1h40".to_string()))
+        );
+    }
+
+    #[test]
+    fn durations_are_rounded_to_minutes() {
+        assert_eq!(format_duration(Duration::from_secs(29)), "0min");
+        assert_eq!(format_duration(Duration::from_secs(25 * 60 + 40)), "26min");
+        assert_eq!(format_duration(Duration::from_secs(59 * 60 + 30)), "1h00");
+        assert_eq!(
+            format_duration(Duration::from_secs(47 * 3600 + 5 * 60)),
+            "47h05"
+        );
+    }
+}
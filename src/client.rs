@@ -1,8 +1,16 @@
 use anyhow::anyhow;
+use embedded_websocket::framer::Framer;
 use embedded_websocket::WebSocketClient;
 use embedded_websocket::WebSocketOptions;
+use embedded_websocket::WebSocketSendMessageType;
 use rand::rngs::ThreadRng;
 use std::net::TcpStream;
+use std::time::Duration;
+
+// sent to the server to get a message with totals and weather data again
+const FULL_STATE_REQUEST: &str = r#"{"request":"full_state"}"#;
+// reads return after this so we can notice a server that stopped sending
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub fn convert_connect_error(
     err: embedded_websocket::framer::FramerError<std::io::Error>,
@@ -22,6 +30,7 @@ pub fn create_tcp_conn_and_client(
     addr: &str,
 ) -> anyhow::Result<(TcpStream, WebSocketOptions, WebSocketClient<ThreadRng>)> {
     let stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
     let client = WebSocketClient::new_client(rand::thread_rng());
     let websocket_options = WebSocketOptions {
         path: "/subscribe",
@@ -32,3 +41,28 @@ pub fn create_tcp_conn_and_client(
     };
     return Ok((stream, websocket_options, client));
 }
+pub fn request_full_state(
+    framer: &mut Framer<ThreadRng, embedded_websocket::Client>,
+    stream: &mut TcpStream,
+) -> anyhow::Result<()> {
+    framer
+        .write(
+            stream,
+            WebSocketSendMessageType::Text,
+            true,
+            FULL_STATE_REQUEST.as_bytes(),
+        )
+        .map_err(convert_connect_error)?;
+    Ok(())
+}
+
+// true when the read only hit the timeout and the connection is still usable
+pub fn is_read_timeout(err: &embedded_websocket::framer::FramerError<std::io::Error>) -> bool {
+    match err {
+        embedded_websocket::framer::FramerError::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ),
+        _ => false,
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use std::time::SystemTime;
+
+use crate::freshness::parse_timestamp;
+
+// the device clock set by sntp, only on the esp32
+#[cfg(target_os = "espidf")]
+mod sntp {
+    use chrono::{DateTime, Utc};
+    use esp_idf_svc::sntp::{EspSntp, SntpConf, SyncStatus};
+    use std::time::{Duration, Instant, SystemTime};
+
+    use super::utc_now;
+    use crate::freshness::device_time_valid;
+
+    // sntp resyncs every hour, after this long without one the clock is considered unsynced
+    const MAX_SYNC_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+
+    pub struct Clock {
+        // keeps the sntp service running
+        sntp: EspSntp<'static>,
+        last_sync: Option<Instant>,
+    }
+
+    impl Clock {
+        // only as many servers as CONFIG_LWIP_SNTP_MAX_SERVERS allows are used
+        pub fn start(servers: &[&'static str]) -> anyhow::Result<Self> {
+            let mut conf = SntpConf::default();
+            if !servers.is_empty() {
+                for (slot, server) in conf.servers.iter_mut().zip(servers.iter()) {
+                    *slot = *server;
+                }
+                if servers.len() > conf.servers.len() {
+                    log::warn!("Only the first {} ntp servers are used", conf.servers.len());
+                }
+            }
+            let sntp = EspSntp::new(&conf)?;
+            log::info!("Started sntp with servers {:?}", conf.servers);
+            Ok(Clock {
+                sntp,
+                last_sync: None,
+            })
+        }
+        // the status is only reported as completed once per sync, so it is remembered here
+        pub fn poll(&mut self) {
+            if matches!(self.sntp.get_sync_status(), SyncStatus::Completed) {
+                if self.last_sync.is_none() {
+                    log::info!("Time synchronized: {}", utc_now());
+                }
+                self.last_sync = Some(Instant::now());
+            }
+        }
+        pub fn is_synced(&mut self) -> bool {
+            self.poll();
+            match self.last_sync {
+                Some(last_sync) => {
+                    last_sync.elapsed() < MAX_SYNC_AGE && device_time_valid(SystemTime::now())
+                }
+                None => false,
+            }
+        }
+        // None until the clock has been set
+        pub fn now(&mut self) -> Option<DateTime<Utc>> {
+            match self.is_synced() {
+                true => Some(utc_now()),
+                false => None,
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "espidf")]
+pub use sntp::Clock;
+
+// how times are shown, converted to the configured time zone with its dst rules
+#[derive(Debug, Clone, Copy)]
+pub struct TimeFormat {
+    tz: Tz,
+    twelve_hour: bool,
+}
+
+impl TimeFormat {
+    pub fn new(tz: Tz, twelve_hour: bool) -> Self {
+        TimeFormat { tz, twelve_hour }
+    }
+    // "14:05" or "2:05PM"
+    pub fn time(&self, time: DateTime<Utc>) -> String {
+        let local = time.with_timezone(&self.tz);
+        match self.twelve_hour {
+            true => local.format("%-I:%M%p").to_string(),
+            false => local.format("%H:%M").to_string(),
+        }
+    }
+    // "14:00" or "2PM", used to label the forecast columns
+    pub fn hour(&self, time: DateTime<Utc>) -> String {
+        let local = time.with_timezone(&self.tz);
+        match self.twelve_hour {
+            true => local.format("%-I%p").to_string(),
+            false => local.format("%H:%M").to_string(),
+        }
+    }
+    // the calendar day in the configured time zone, daily counters reset when it changes
+    pub fn local_date(&self, time: DateTime<Utc>) -> NaiveDate {
+        time.with_timezone(&self.tz).date_naive()
+    }
+    // minutes since local midnight, selects the time of use band of the tariff
+    pub fn minute_of_day(&self, time: DateTime<Utc>) -> u16 {
+        let local = time.with_timezone(&self.tz);
+        (local.hour() * 60 + local.minute()) as u16
+    }
+    // unix seconds of the last local midnight
+    pub fn day_start(&self, time: DateTime<Utc>) -> u32 {
+        let local = time.with_timezone(&self.tz);
+        let midnight = local.date_naive().and_time(NaiveTime::MIN);
+        // some zones skip midnight when dst starts
+        let start = match self.tz.from_local_datetime(&midnight).earliest() {
+            Some(start) => start.timestamp(),
+            None => time.timestamp() - local.num_seconds_from_midnight() as i64,
+        };
+        start.max(0) as u32
+    }
+    // time of day of unix seconds, like the peaks are stored
+    pub fn unix_time(&self, ts: u32) -> String {
+        match DateTime::from_timestamp(ts as i64, 0) {
+            Some(time) => self.time(time),
+            None => "--:--".to_string(),
+        }
+    }
+    // "2024-06-21", the same in every language so it isn't mixed up
+    pub fn unix_date(&self, ts: u32) -> String {
+        match DateTime::from_timestamp(ts as i64, 0) {
+            Some(time) => time.with_timezone(&self.tz).format("%Y-%m-%d").to_string(),
+            None => "----------".to_string(),
+        }
+    }
+    // strings that are not a timestamp, like the "2:05PM" older servers send, are kept as they are
+    pub fn timestamp(&self, ts: &str) -> String {
+        match parse_timestamp(ts, self.tz) {
+            Some(time) => self.time(time),
+            None => ts.to_string(),
+        }
+    }
+    pub fn hour_timestamp(&self, ts: &str) -> String {
+        match parse_timestamp(ts, self.tz) {
+            Some(time) => self.hour(time),
+            None => ts.to_string(),
+        }
+    }
+}
+
+// a window of local time, may wrap around midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    // minutes since local midnight, the end is exclusive
+    pub start: u16,
+    pub end: u16,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, minute: u16) -> bool {
+        match self.start <= self.end {
+            true => minute >= self.start && minute < self.end,
+            false => minute >= self.start || minute < self.end,
+        }
+    }
+}
+
+// "22:00-06:00"
+pub fn parse_window(window: &str) -> anyhow::Result<TimeWindow> {
+    let (start, end) = window
+        .split_once('-')
+        .ok_or(anyhow!("missing end time in {}", window))?;
+    Ok(TimeWindow {
+        start: parse_minute(start)?,
+        end: parse_minute(end)?,
+    })
+}
+
+// "06:30" as minutes since midnight
+pub fn parse_minute(time: &str) -> anyhow::Result<u16> {
+    let (hour, minute) = time
+        .trim()
+        .split_once(':')
+        .ok_or(anyhow!("invalid time {}", time))?;
+    let (hour, minute) = (hour.parse::<u16>()?, minute.parse::<u16>()?);
+    // 24:00 is allowed as the end of the day
+    if hour > 24 || minute > 59 || (hour == 24 && minute != 0) {
+        return Err(anyhow!("invalid time {}", time));
+    }
+    Ok(hour * 60 + minute)
+}
+
+// 0 before 1970, which only happens with a broken clock
+pub fn unix_time() -> u32 {
+    utc_now().timestamp().max(0) as u32
+}
+
+fn utc_now() -> DateTime<Utc> {
+    SystemTime::now().into()
+}
@@ -0,0 +1,63 @@
+use crate::energy::EnergyTotals;
+use crate::state::Language;
+
+// emissions the pv energy avoided, the grid would have produced it otherwise
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Co2Counter {
+    // g CO2 per kWh of the grid
+    factor: f32,
+    // kg of the current local day
+    pub today: f32,
+    // kg since the first start, kept in flash
+    pub total: f32,
+}
+
+impl Co2Counter {
+    pub fn new(factor: f32, total: f32) -> Self {
+        Co2Counter {
+            factor,
+            today: 0.0,
+            total,
+        }
+    }
+    // energy is the delta since the last message
+    pub fn add(&mut self, energy: &EnergyTotals) {
+        let avoided = avoided_kg(energy, self.factor);
+        self.today += avoided;
+        self.total += avoided;
+    }
+    pub fn reset_day(&mut self) {
+        self.today = 0.0;
+    }
+}
+
+// the self consumed pv energy replaces grid import, the exported energy replaces grid
+// generation elsewhere. export beyond the generation comes from the battery, its pv energy
+// was already counted when it was charged
+pub fn avoided_kg(energy: &EnergyTotals, factor: f32) -> f32 {
+    let self_consumed = (energy.generation - energy.grid_export).max(0.0);
+    (self_consumed + energy.grid_export.min(energy.generation)) * factor / 1000.0
+}
+
+// in front of the total on the dashboard
+pub fn total_label(language: Language) -> &'static str {
+    match language {
+        Language::En => "total",
+        Language::De => "gesamt",
+    }
+}
+
+// at most 5 characters, "850g", "2.4kg", "815kg" or "1.2t"
+pub fn format_mass(kg: f32) -> String {
+    if kg < 0.9995 {
+        format!("{:.0}g", kg.max(0.0) * 1000.0)
+    } else if kg < 9.95 {
+        format!("{:.1}kg", kg)
+    } else if kg < 999.5 {
+        format!("{:.0}kg", kg)
+    } else if kg < 9950.0 {
+        format!("{:.1}t", kg / 1000.0)
+    } else {
+        format!("{:.0}t", (kg / 1000.0).min(9999.0))
+    }
+}
@@ -0,0 +1,182 @@
+use crate::types::parse_number;
+
+// rain or showers in mm per hour below this count as dry
+const MIN_PRECIPITATION: f32 = 0.1;
+// cloud cover in percent
+const MAX_CLEAR: f32 = 20.0;
+const MIN_OVERCAST: f32 = 80.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Overcast,
+    Rain,
+    Showers,
+    Thunderstorm,
+    Snow,
+}
+
+impl Condition {
+    // the groups of the wmo weather interpretation codes, fog counts as overcast
+    pub fn from_wmo(code: u8) -> Option<Condition> {
+        match code {
+            0 | 1 => Some(Condition::Clear),
+            2 => Some(Condition::PartlyCloudy),
+            3 | 45 | 48 => Some(Condition::Overcast),
+            51..=67 => Some(Condition::Rain),
+            71..=77 | 85 | 86 => Some(Condition::Snow),
+            80..=82 => Some(Condition::Showers),
+            95..=99 => Some(Condition::Thunderstorm),
+            _ => None,
+        }
+    }
+}
+
+// the values of one forecast hour as the server sends them
+pub struct HourValues<'a> {
+    pub cloud_cover: &'a str,
+    pub rain: &'a str,
+    pub showers: &'a str,
+    pub temperature: Option<&'a str>,
+    pub weather_code: Option<&'a str>,
+}
+
+// a known weather code wins, otherwise the values decide. thunderstorms can only come
+// from the code, snow needs it or a temperature at or below zero
+pub fn classify(hour: &HourValues) -> Condition {
+    if let Some(condition) = hour
+        .weather_code
+        .and_then(parse_number)
+        .filter(|code| (0.0..=255.0).contains(code))
+        .and_then(|code| Condition::from_wmo(code as u8))
+    {
+        return condition;
+    }
+    let value = |value: &str| parse_number(value).unwrap_or(0.0);
+    let (rain, showers) = (value(hour.rain), value(hour.showers));
+    if rain.max(showers) >= MIN_PRECIPITATION {
+        let freezing = hour
+            .temperature
+            .and_then(parse_number)
+            .is_some_and(|temperature| temperature <= 0.0);
+        return match (freezing, showers > rain) {
+            (true, _) => Condition::Snow,
+            (false, true) => Condition::Showers,
+            (false, false) => Condition::Rain,
+        };
+    }
+    match value(hour.cloud_cover) {
+        cloud if cloud < MAX_CLEAR => Condition::Clear,
+        cloud if cloud < MIN_OVERCAST => Condition::PartlyCloudy,
+        _ => Condition::Overcast,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hour<'a>(cloud_cover: &'a str, rain: &'a str, showers: &'a str) -> HourValues<'a> {
+        HourValues {
+            cloud_cover,
+            rain,
+            showers,
+            temperature: Some("10.0"),
+            weather_code: None,
+        }
+    }
+
+    #[test]
+    fn wmo_groups() {
+        let groups = [
+            (0, Condition::Clear),
+            (1, Condition::Clear),
+            (2, Condition::PartlyCloudy),
+            (3, Condition::Overcast),
+            (45, Condition::Overcast),
+            (48, Condition::Overcast),
+            (51, Condition::Rain),
+            (67, Condition::Rain),
+            (71, Condition::Snow),
+            (77, Condition::Snow),
+            (80, Condition::Showers),
+            (82, Condition::Showers),
+            (85, Condition::Snow),
+            (86, Condition::Snow),
+            (95, Condition::Thunderstorm),
+            (99, Condition::Thunderstorm),
+        ];
+        for (code, condition) in groups {
+            assert_eq!(Condition::from_wmo(code), Some(condition), "{}", code);
+        }
+        for code in [4, 42, 50, 68, 78, 83, 90, 100, 255] {
+            assert_eq!(Condition::from_wmo(code), None, "{}", code);
+        }
+    }
+
+    #[test]
+    fn the_code_wins_over_the_values() {
+        let mut hour = hour("0", "0.0", "0.0");
+        hour.weather_code = Some("95");
+        assert_eq!(classify(&hour), Condition::Thunderstorm);
+        hour.weather_code = Some("3.0");
+        assert_eq!(classify(&hour), Condition::Overcast);
+        // dry values but a snow code in the warm
+        hour.weather_code = Some("73");
+        assert_eq!(classify(&hour), Condition::Snow);
+        // unknown or broken codes fall back to the values
+        for code in ["42", "-1", "300", "", "fog"] {
+            hour.weather_code = Some(code);
+            assert_eq!(classify(&hour), Condition::Clear, "{}", code);
+        }
+    }
+
+    #[test]
+    fn precipitation_threshold() {
+        assert_eq!(
+            classify(&hour("50", "0.09", "0.0")),
+            Condition::PartlyCloudy
+        );
+        assert_eq!(classify(&hour("50", "0.1", "0.0")), Condition::Rain);
+        assert_eq!(classify(&hour("50", "0.0", "0.1")), Condition::Showers);
+    }
+
+    #[test]
+    fn snow_at_or_below_zero() {
+        let mut hour = hour("100", "0.3", "0.0");
+        hour.temperature = Some("0.0");
+        assert_eq!(classify(&hour), Condition::Snow);
+        hour.temperature = Some("-1.5");
+        assert_eq!(classify(&hour), Condition::Snow);
+        hour.temperature = Some("0.1");
+        assert_eq!(classify(&hour), Condition::Rain);
+        // an unknown temperature is not freezing
+        hour.temperature = None;
+        assert_eq!(classify(&hour), Condition::Rain);
+        hour.temperature = Some("x");
+        assert_eq!(classify(&hour), Condition::Rain);
+    }
+
+    #[test]
+    fn showers_only_when_they_outweigh_the_rain() {
+        assert_eq!(classify(&hour("60", "0.1", "0.4")), Condition::Showers);
+        assert_eq!(classify(&hour("60", "0.4", "0.4")), Condition::Rain);
+        assert_eq!(classify(&hour("60", "0.5", "0.2")), Condition::Rain);
+    }
+
+    #[test]
+    fn cloud_bands() {
+        assert_eq!(classify(&hour("0", "0.0", "0.0")), Condition::Clear);
+        assert_eq!(classify(&hour("19.9", "0.0", "0.0")), Condition::Clear);
+        assert_eq!(classify(&hour("20", "0.0", "0.0")), Condition::PartlyCloudy);
+        assert_eq!(
+            classify(&hour("79.9", "0.0", "0.0")),
+            Condition::PartlyCloudy
+        );
+        assert_eq!(classify(&hour("80", "0.0", "0.0")), Condition::Overcast);
+        assert_eq!(classify(&hour("100", "", "")), Condition::Overcast);
+        // missing values count as a clear sky
+        assert_eq!(classify(&hour("", "", "")), Condition::Clear);
+    }
+}
@@ -0,0 +1,178 @@
+use chrono_tz::Tz;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::clock::{self, TimeFormat};
+use crate::refresh::RefreshThresholds;
+use crate::state::Language;
+use crate::tariff::{self, Tariff};
+
+// optional settings from the .env file, build.rs only passes them on when they are set
+pub struct Config {
+    // data older than this is marked as stale on the display
+    pub stale_after: Duration,
+    // comma separated, a local server can be used on lans without internet access
+    pub ntp_servers: Vec<&'static str>,
+    // iana name like Europe/Berlin
+    pub timezone: Tz,
+    pub twelve_hour: bool,
+    // en or de, used for the system state labels
+    pub language: Language,
+    // seconds averaged into one sample of the power history
+    pub history_resolution: u32,
+    // minimum time between writes of the last state and history to flash
+    pub persist_interval: Duration,
+    // relative difference between server and local daily totals that is marked, 0.1 for 10%
+    pub energy_deviation: f32,
+    // only set when an import price is configured
+    pub tariff: Option<Tariff>,
+    // shown next to the savings
+    pub currency: &'static str,
+    // g CO2 per kWh of grid energy, used for the avoided emissions
+    pub co2_factor: f32,
+    // usable kWh, the time until the battery is full or empty is only shown when it is set
+    pub battery_capacity: Option<f32>,
+    // how long the dashboard and the records page are shown, 0 leaves the records page out
+    pub dashboard_page: Duration,
+    pub chart_page: Duration,
+    pub records_page: Duration,
+    pub periods_page: Duration,
+    // span of the power history chart
+    pub chart_hours: u32,
+    // hours of the forecast chart, without it the forecast is shown as columns of numbers
+    pub forecast_chart: Option<usize>,
+    // state of charge in percent below which the battery is highlighted
+    pub low_battery: f32,
+    // when the ghosting of quick refreshes is cleared with a full refresh
+    pub refresh: RefreshThresholds,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config {
+            stale_after: Duration::from_secs(parse_or(
+                "STALE_AFTER_SECS",
+                option_env!("STALE_AFTER_SECS"),
+                300,
+            )),
+            ntp_servers: option_env!("NTP_SERVERS")
+                .unwrap_or("pool.ntp.org")
+                .split(',')
+                .map(str::trim)
+                .filter(|server| !server.is_empty())
+                .collect(),
+            timezone: parse_or("TIMEZONE", option_env!("TIMEZONE"), Tz::UTC),
+            twelve_hour: option_env!("CLOCK_FORMAT").map(str::trim) == Some("12h"),
+            language: parse_or("LANGUAGE", option_env!("LANGUAGE"), Language::En),
+            history_resolution: parse_or(
+                "HISTORY_RESOLUTION_SECS",
+                option_env!("HISTORY_RESOLUTION_SECS"),
+                300,
+            ),
+            persist_interval: Duration::from_secs(parse_or(
+                "PERSIST_INTERVAL_SECS",
+                option_env!("PERSIST_INTERVAL_SECS"),
+                900,
+            )),
+            energy_deviation: parse_or(
+                "ENERGY_DEVIATION_PERCENT",
+                option_env!("ENERGY_DEVIATION_PERCENT"),
+                10.0,
+            ) / 100.0,
+            tariff: option_env!("TARIFF_IMPORT").map(|import| Tariff {
+                import_price: parse_or("TARIFF_IMPORT", Some(import), 0.0),
+                feed_in: parse_or("TARIFF_FEED_IN", option_env!("TARIFF_FEED_IN"), 0.08),
+                bands: match tariff::parse_bands(option_env!("TARIFF_BANDS").unwrap_or("")) {
+                    Ok(bands) => bands,
+                    Err(e) => {
+                        log::warn!("Invalid value for TARIFF_BANDS, ignoring them: {:?}", e);
+                        Vec::new()
+                    }
+                },
+            }),
+            currency: option_env!("CURRENCY").map_or("EUR", str::trim),
+            co2_factor: parse_or("CO2_FACTOR", option_env!("CO2_FACTOR"), 380.0),
+            battery_capacity: option_env!("BATTERY_CAPACITY_KWH").and_then(
+                |capacity| match capacity.trim().parse::<f32>() {
+                    Ok(capacity) if capacity > 0.0 => Some(capacity),
+                    _ => {
+                        log::warn!("Invalid value for BATTERY_CAPACITY_KWH, ignoring it");
+                        None
+                    }
+                },
+            ),
+            dashboard_page: Duration::from_secs(parse_or(
+                "DASHBOARD_PAGE_SECS",
+                option_env!("DASHBOARD_PAGE_SECS"),
+                300,
+            )),
+            chart_page: Duration::from_secs(parse_or(
+                "CHART_PAGE_SECS",
+                option_env!("CHART_PAGE_SECS"),
+                20,
+            )),
+            records_page: Duration::from_secs(parse_or(
+                "RECORDS_PAGE_SECS",
+                option_env!("RECORDS_PAGE_SECS"),
+                20,
+            )),
+            periods_page: Duration::from_secs(parse_or(
+                "PERIODS_PAGE_SECS",
+                option_env!("PERIODS_PAGE_SECS"),
+                20,
+            )),
+            chart_hours: parse_or("CHART_HOURS", option_env!("CHART_HOURS"), 6).max(1),
+            forecast_chart: option_env!("FORECAST_CHART_HOURS").and_then(|hours| {
+                match hours.trim().parse::<usize>() {
+                    Ok(hours) if hours > 0 => Some(hours),
+                    _ => {
+                        log::warn!("Invalid value for FORECAST_CHART_HOURS, ignoring it");
+                        None
+                    }
+                }
+            }),
+            low_battery: parse_or(
+                "LOW_BATTERY_PERCENT",
+                option_env!("LOW_BATTERY_PERCENT"),
+                20.0,
+            ),
+            refresh: RefreshThresholds {
+                max_quick: parse_or("REFRESH_MAX_QUICK", option_env!("REFRESH_MAX_QUICK"), 60),
+                max_area: parse_or("REFRESH_MAX_AREA", option_env!("REFRESH_MAX_AREA"), 3.0),
+                min_interval: Duration::from_secs(parse_or(
+                    "REFRESH_MIN_INTERVAL_SECS",
+                    option_env!("REFRESH_MIN_INTERVAL_SECS"),
+                    120,
+                )),
+                quiet_hours: option_env!("QUIET_HOURS").and_then(
+                    |hours| match clock::parse_window(hours) {
+                        Ok(hours) => Some(hours),
+                        Err(e) => {
+                            log::warn!("Invalid value for QUIET_HOURS, ignoring it: {:?}", e);
+                            None
+                        }
+                    },
+                ),
+                slow_interval: Duration::from_secs(parse_or(
+                    "REFRESH_SLOW_INTERVAL_SECS",
+                    option_env!("REFRESH_SLOW_INTERVAL_SECS"),
+                    30,
+                )),
+            },
+        }
+    }
+    pub fn time_format(&self) -> TimeFormat {
+        TimeFormat::new(self.timezone, self.twelve_hour)
+    }
+}
+
+fn parse_or<T: FromStr>(key: &str, value: Option<&str>, default: T) -> T {
+    match value.map(|value| value.trim().parse::<T>()) {
+        Some(Ok(value)) => value,
+        Some(Err(_)) => {
+            log::warn!("Invalid value for {}, using the default", key);
+            default
+        }
+        None => default,
+    }
+}
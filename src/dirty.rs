@@ -0,0 +1,136 @@
+use embedded_graphics::prelude::{Point, Size};
+use embedded_graphics::primitives::Rectangle;
+
+use crate::widget;
+
+// every window costs a few spi commands, past this many they are drawn as one
+const MAX_REGIONS: usize = 6;
+
+// the parts of the screen that were drawn to since the panel was last refreshed
+pub struct DirtyRegions {
+    screen: Rectangle,
+    regions: Vec<Rectangle>,
+}
+
+impl DirtyRegions {
+    pub fn new(size: Size) -> Self {
+        DirtyRegions {
+            screen: Rectangle::new(Point::zero(), size),
+            regions: Vec::new(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+    pub fn mark(&mut self, area: Rectangle) {
+        // text and lines can reach past the screen edges
+        let mut merged = area.intersection(&self.screen);
+        if merged.is_zero_sized() {
+            return;
+        }
+        // overlapping or touching regions would be sent twice, so they are joined
+        while let Some(idx) = self
+            .regions
+            .iter()
+            .position(|region| touches(region, &merged))
+        {
+            merged = widget::envelope(&merged, &self.regions.swap_remove(idx));
+        }
+        self.regions.push(merged);
+        if self.regions.len() > MAX_REGIONS {
+            let all = self
+                .regions
+                .iter()
+                .fold(merged, |all, region| widget::envelope(&all, region));
+            self.regions.clear();
+            self.regions.push(all);
+        }
+    }
+    pub fn mark_all(&mut self) {
+        self.regions.clear();
+        self.regions.push(self.screen);
+    }
+    pub fn take(&mut self) -> Vec<Rectangle> {
+        std::mem::take(&mut self.regions)
+    }
+}
+
+// also true for regions that only share an edge
+fn touches(a: &Rectangle, b: &Rectangle) -> bool {
+    let (a_end, b_end) = (a.top_left + a.size, b.top_left + b.size);
+    a.top_left.x <= b_end.x
+        && b.top_left.x <= a_end.x
+        && a.top_left.y <= b_end.y
+        && b.top_left.y <= a_end.y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn regions() -> DirtyRegions {
+        DirtyRegions::new(Size::new(296, 128))
+    }
+
+    #[test]
+    fn separate_regions_are_kept_apart() {
+        let mut dirty = regions();
+        assert!(dirty.is_empty());
+        dirty.mark(rect(0, 0, 10, 10));
+        dirty.mark(rect(50, 50, 10, 10));
+        assert_eq!(dirty.take(), vec![rect(0, 0, 10, 10), rect(50, 50, 10, 10)]);
+        assert!(dirty.is_empty());
+    }
+
+    #[test]
+    fn overlapping_and_touching_regions_are_merged() {
+        let mut dirty = regions();
+        dirty.mark(rect(0, 0, 10, 10));
+        dirty.mark(rect(5, 5, 10, 10));
+        assert_eq!(dirty.take(), vec![rect(0, 0, 15, 15)]);
+        // sharing an edge
+        dirty.mark(rect(0, 0, 10, 10));
+        dirty.mark(rect(10, 0, 10, 10));
+        assert_eq!(dirty.take(), vec![rect(0, 0, 20, 10)]);
+        // a merge can reach a third region
+        dirty.mark(rect(0, 0, 10, 10));
+        dirty.mark(rect(30, 0, 10, 10));
+        dirty.mark(rect(8, 0, 24, 4));
+        assert_eq!(dirty.take(), vec![rect(0, 0, 40, 10)]);
+    }
+
+    #[test]
+    fn regions_are_clipped_to_the_screen() {
+        let mut dirty = regions();
+        dirty.mark(rect(-5, -5, 10, 10));
+        dirty.mark(rect(290, 120, 20, 20));
+        dirty.mark(rect(300, 0, 10, 10));
+        dirty.mark(rect(0, 0, 0, 0));
+        assert_eq!(dirty.take(), vec![rect(0, 0, 5, 5), rect(290, 120, 6, 8)]);
+    }
+
+    #[test]
+    fn many_regions_collapse_into_one() {
+        let mut dirty = regions();
+        for idx in 0..MAX_REGIONS as i32 {
+            dirty.mark(rect(idx * 20, idx * 10, 5, 5));
+        }
+        assert_eq!(dirty.take().len(), MAX_REGIONS);
+        for idx in 0..=MAX_REGIONS as i32 {
+            dirty.mark(rect(idx * 20, idx * 10, 5, 5));
+        }
+        assert_eq!(dirty.take(), vec![rect(0, 0, 125, 65)]);
+    }
+
+    #[test]
+    fn mark_all_covers_the_screen() {
+        let mut dirty = regions();
+        dirty.mark(rect(0, 0, 10, 10));
+        dirty.mark_all();
+        assert_eq!(dirty.take(), vec![rect(0, 0, 296, 128)]);
+    }
+}
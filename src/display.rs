@@ -1,83 +1,128 @@
 use anyhow::anyhow;
+use core::convert::Infallible;
 use embedded_graphics::draw_target::DrawTarget;
-use embedded_graphics::iterator::PixelIteratorExt;
 use embedded_graphics::mono_font::MonoTextStyleBuilder;
-use embedded_graphics::prelude::Size;
+use embedded_graphics::Pixel;
 
+use embedded_graphics::mono_font::MonoFont;
 use embedded_graphics::mono_font::MonoTextStyle;
 use embedded_graphics::pixelcolor::BinaryColor;
-use embedded_graphics::prelude::OriginDimensions;
-use embedded_graphics::prelude::Point;
+use embedded_graphics::prelude::{OriginDimensions, Point, Size};
 use embedded_graphics::primitives::*;
+use embedded_graphics::text::Alignment;
+use embedded_graphics::text::Baseline;
 use embedded_graphics::text::Text;
+use embedded_graphics::text::TextStyleBuilder;
 use embedded_graphics::Drawable;
-use embedded_graphics::Pixel;
 
+use epd_waveshare::prelude;
 use epd_waveshare::prelude::Display;
 use epd_waveshare::prelude::DisplayRotation;
-use esp_idf_hal::delay::Ets;
-use esp_idf_hal::gpio::Gpio10;
-use esp_idf_hal::gpio::Gpio17;
-use esp_idf_hal::gpio::Gpio18;
-use esp_idf_hal::gpio::Gpio21;
-use esp_idf_hal::gpio::Gpio38;
-use esp_idf_hal::gpio::Gpio48;
-use esp_idf_hal::spi::SPI2;
-use esp_idf_hal::units::Hertz;
-
-use epd_waveshare::epd2in9_v2::Epd2in9;
-use esp_idf_hal::gpio;
-use esp_idf_hal::gpio::Input;
-use esp_idf_hal::gpio::Output;
-use esp_idf_hal::gpio::PinDriver;
-use esp_idf_hal::spi;
-use esp_idf_hal::spi::SpiDeviceDriver;
-use esp_idf_hal::spi::SpiDriver;
-
-use esp_idf_hal::delay;
 
-use epd_waveshare::{prelude::WaveshareDisplay, *};
-
-use crate::types::HourlyNew;
-// this is for the direction power is comming from
-enum ArrowDirection {
-    Left,
-    Right,
-    Down,
-    Up,
-}
-// this is for sunset and sunrise
-enum SimpleArrowDirection {
-    Up,
-    Down,
-}
+use crate::condition::{self, Condition, HourValues};
+use crate::dirty::DirtyRegions;
+use crate::icons;
+use crate::panel;
+use crate::types::{parse_number, HourlyNew};
+use crate::widget::{
+    self, Bitmap, CircleFrame, ColorRole, Icon, Label, Layout, Separator, Sparkline, Stroke, Widget,
+};
+// this is for the direction power is comming from, true draws the arrow at the outer end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionDirection {
     Top(bool),
     Right(bool),
     Left(bool),
     Bottom(bool),
 }
+
 // trying to avoid stack overflows  so we use heap alloc
-pub struct DisplayBoxed(Box<epd2in9_v2::Display2in9>);
+pub struct DisplayBoxed {
+    layers: Layers,
+    layout: Layout,
+    dashboard: Dashboard,
+    // hours of the forecast chart, the forecast is shown as columns without it
+    forecast_hours: Option<usize>,
+}
+
+// the black buffer and, on three color panels, the red one
+struct Layers {
+    black: Box<panel::Buffer>,
+    chromatic: Option<Box<panel::Buffer>>,
+    // what the next quick refresh sends to the panel
+    dirty: DirtyRegions,
+}
+
+impl Layers {
+    fn new() -> Self {
+        Layers {
+            black: Box::new(panel::Buffer::default()),
+            chromatic: match panel::THREE_COLOR {
+                true => Some(Box::new(panel::Buffer::default())),
+                false => None,
+            },
+            dirty: DirtyRegions::new(panel::size()),
+        }
+    }
+    // highlights end up in black on panels without a third color
+    fn target(&mut self, role: ColorRole) -> &mut panel::Buffer {
+        match (role, self.chromatic.as_mut()) {
+            (ColorRole::Highlight, Some(chromatic)) => chromatic.as_mut(),
+            _ => self.black.as_mut(),
+        }
+    }
+    fn clear_region(&mut self, area: &Rectangle) -> anyhow::Result<()> {
+        self.dirty.mark(*area);
+        self.black.fill_solid(area, BinaryColor::Off)?;
+        if let Some(chromatic) = self.chromatic.as_mut() {
+            chromatic.fill_solid(area, BinaryColor::Off)?;
+        }
+        Ok(())
+    }
+    fn draw<W: Widget>(&mut self, widget: &W) -> anyhow::Result<()> {
+        self.dirty.mark(widget.bounds());
+        widget.draw(self.target(widget.role()))
+    }
+    // the region is cleared in both layers, the widget may have changed its role since
+    fn redraw<W: Widget>(&mut self, widget: &W) -> anyhow::Result<()> {
+        self.clear_region(&widget.bounds())?;
+        self.draw(widget)
+    }
+}
 
 impl DrawTarget for DisplayBoxed {
     type Color = BinaryColor;
     type Error = core::convert::Infallible;
     fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
-        self.0.clear(color)
+        self.layers.dirty.mark_all();
+        if let Some(chromatic) = self.layers.chromatic.as_mut() {
+            chromatic.clear(BinaryColor::Off)?;
+        }
+        self.layers.black.clear(color)
     }
     fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
     where
         I: IntoIterator<Item = embedded_graphics::Pixel<Self::Color>>,
     {
-        self.0.draw_iter(pixels)
+        let mut drawn: Option<Rectangle> = None;
+        self.layers
+            .black
+            .draw_iter(pixels.into_iter().inspect(|Pixel(point, _)| {
+                let pixel = Rectangle::new(*point, Size::new(1, 1));
+                drawn = Some(drawn.map_or(pixel, |drawn| widget::envelope(&drawn, &pixel)));
+            }))?;
+        if let Some(drawn) = drawn {
+            self.layers.dirty.mark(drawn);
+        }
+        Ok(())
     }
     fn fill_solid(
         &mut self,
         area: &embedded_graphics::primitives::Rectangle,
         color: Self::Color,
     ) -> Result<(), Self::Error> {
-        self.0.fill_solid(area, color)
+        self.layers.dirty.mark(*area);
+        self.layers.black.fill_solid(area, color)
     }
     fn fill_contiguous<I>(
         &mut self,
@@ -87,15 +132,16 @@ impl DrawTarget for DisplayBoxed {
     where
         I: IntoIterator<Item = Self::Color>,
     {
-        self.0.fill_contiguous(area, colors)
+        self.layers.dirty.mark(*area);
+        self.layers.black.fill_contiguous(area, colors)
     }
 }
 impl Display for DisplayBoxed {
     fn buffer(&self) -> &[u8] {
-        self.0.buffer()
+        self.layers.black.buffer()
     }
     fn rotation(&self) -> DisplayRotation {
-        self.0.rotation()
+        self.layers.black.rotation()
     }
     fn draw_helper(
         &mut self,
@@ -103,1188 +149,1283 @@ impl Display for DisplayBoxed {
         height: u32,
         pixel: embedded_graphics::Pixel<BinaryColor>,
     ) -> Result<(), Self::Error> {
-        self.0.draw_helper(width, height, pixel)
+        self.layers
+            .dirty
+            .mark(Rectangle::new(pixel.0, Size::new(1, 1)));
+        self.layers.black.draw_helper(width, height, pixel)
     }
     fn clear_buffer(&mut self, background_color: prelude::Color) {
-        self.0.clear_buffer(background_color)
+        self.layers.dirty.mark_all();
+        if let Some(chromatic) = self.layers.chromatic.as_mut() {
+            chromatic.clear_buffer(prelude::Color::White);
+        }
+        self.layers.black.clear_buffer(background_color)
     }
     fn set_rotation(&mut self, rotation: DisplayRotation) {
-        self.0.set_rotation(rotation)
+        if let Some(chromatic) = self.layers.chromatic.as_mut() {
+            chromatic.set_rotation(rotation);
+        }
+        self.layers.black.set_rotation(rotation)
     }
     fn get_mut_buffer(&mut self) -> &mut [u8] {
-        self.0.get_mut_buffer()
+        // anything could be changed through it
+        self.layers.dirty.mark_all();
+        self.layers.black.get_mut_buffer()
     }
 }
 
 impl OriginDimensions for DisplayBoxed {
     fn size(&self) -> embedded_graphics::prelude::Size {
-        self.0.size()
+        self.layers.black.size()
     }
 }
 
-pub fn init_display<'a>(
-    spi2: SPI2,
-    gpio48: Gpio48,
-    gpio38: Gpio38,
-    gpio21: Gpio21,
-    gpio10: Gpio10,
-    gpio18: Gpio18,
-    gpio17: Gpio17,
-) -> anyhow::Result<(
-    DisplayBoxed,
-    Epd2in9<
-        SpiDeviceDriver<'a, SpiDriver<'a>>,
-        PinDriver<'a, Gpio21, Output>,
-        PinDriver<'a, Gpio10, Input>,
-        PinDriver<'a, Gpio18, Output>,
-        PinDriver<'a, Gpio17, Output>,
-        Ets,
-    >,
-    SpiDeviceDriver<'a, SpiDriver<'a>>,
-)> {
-    let mut driver = spi::SpiDeviceDriver::new_single(
-        spi2,
-        gpio48,
-        gpio38,
-        Option::<gpio::AnyIOPin>::None,
-        Option::<gpio::AnyOutputPin>::None,
-        &spi::SpiDriverConfig::new().dma(spi::Dma::Disabled),
-        &spi::SpiConfig::new().baudrate(Hertz::from(26)),
-    )?;
-
-    let cs = gpio::PinDriver::output(gpio21)?;
+// sets up the spi bus and the panel, only on the esp32
+#[cfg(target_os = "espidf")]
+mod driver {
+    use epd_waveshare::prelude::WaveshareDisplay;
+    use esp_idf_hal::delay;
+    use esp_idf_hal::gpio::{self, Gpio10, Gpio17, Gpio18, Gpio21, Gpio38, Gpio48};
+    use esp_idf_hal::spi::{self, SPI2};
+    use esp_idf_hal::units::Hertz;
 
-    let busy = gpio::PinDriver::input(gpio10)?;
+    use super::*;
+    use crate::panel::Panel;
 
-    let dc = gpio::PinDriver::output(gpio18)?;
-
-    let rst = gpio::PinDriver::output(gpio17)?;
-
-    let epd = match epd2in9_v2::Epd2in9::new(&mut driver, cs, busy, dc, rst, &mut delay::Ets) {
-        std::result::Result::Ok(epd) => epd,
-        Err(e) => return Err(anyhow::Error::new(e)),
-    };
+    pub fn init_display<'a>(
+        spi2: SPI2,
+        gpio48: Gpio48,
+        gpio38: Gpio38,
+        gpio21: Gpio21,
+        gpio10: Gpio10,
+        gpio18: Gpio18,
+        gpio17: Gpio17,
+    ) -> anyhow::Result<(DisplayBoxed, Panel<'a>)> {
+        let mut driver = spi::SpiDeviceDriver::new_single(
+            spi2,
+            gpio48,
+            gpio38,
+            Option::<gpio::AnyIOPin>::None,
+            Option::<gpio::AnyOutputPin>::None,
+            &spi::SpiDriverConfig::new().dma(spi::Dma::Disabled),
+            &spi::SpiConfig::new().baudrate(Hertz::from(26)),
+        )?;
 
-    let display = Box::new(epd2in9_v2::Display2in9::default());
-    let mut dis_boxed = DisplayBoxed { 0: display };
+        let cs = gpio::PinDriver::output(gpio21)?;
 
-    dis_boxed.set_rotation(DisplayRotation::Rotate90);
-    dis_boxed.clear(BinaryColor::Off)?;
-    return Ok((dis_boxed, epd, driver));
-}
-#[rustfmt::skip]
-static HOUSE_PATTERN: [u8; 270] = [
-    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
-    0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0,
-    0,0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 
-    0,0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 
-    0,0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 
-    0,0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 
-    0,0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 
-    0,0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
-    0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
-    0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        let busy = gpio::PinDriver::input(gpio10)?;
 
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-#[rustfmt::skip]
-static TEMP:[u8;270]=[
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,1,1,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,1,1,0,0,1,1,0,0,0,0,0,0,
-0,0,0,0,0,1,0,0,0,0,0,0,1,0,0,0,0,0,
-0,0,0,0,0,1,0,0,0,0,0,0,1,0,0,0,0,0,
-0,0,0,0,0,1,0,1,1,1,1,0,1,0,0,0,0,0,
-0,0,0,0,0,0,1,0,0,0,0,1,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,1,1,1,0,0,0,0,0,0,0,
-];
-#[rustfmt::skip]
-static CLOUD:[u8;270]=[
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,1,1,1,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,1,1,1,1,1,0,0,0,0,0,
-0,0,0,0,0,1,1,1,1,1,1,1,1,1,0,0,0,0,
-0,0,0,0,1,1,1,1,1,1,1,1,1,1,0,0,0,0,
-0,0,0,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0,
-0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,
-0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,
-0,0,0,0,1,1,1,1,1,1,1,1,1,1,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-];
-#[rustfmt::skip]
-static RAINDROPS:[u8;270]=[
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,1,1,1,1,1,1,1,0,0,0,0,
-0,0,0,0,0,0,0,1,1,1,1,1,1,1,0,0,0,0,
-0,0,0,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0,
-0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0,
-0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,
-0,0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,
-0,0,0,0,1,1,1,1,1,1,1,1,1,1,0,0,0,0,
-0,0,0,0,0,1,0,1,1,0,1,1,0,1,0,0,0,0,
-0,0,0,0,0,1,1,0,1,1,0,1,1,0,0,0,0,0,
-0,0,0,0,0,0,1,1,0,1,1,0,1,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,1,0,0,0,0,0,0,0,
-0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
-];
+        let dc = gpio::PinDriver::output(gpio18)?;
 
-#[rustfmt::skip]
-static LIGHTNING_BOLT_PATTERN: [u8; 270] = [
-    0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-];
-#[rustfmt::skip]
-static BATTERY_PATTERN: [u8; 270] = [
-    0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-];
+        let rst = gpio::PinDriver::output(gpio17)?;
 
-#[rustfmt::skip]
-static SUN_PATTERN: [u8; 270] = [
-
-    0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0,
-    0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0,
-    0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
-    0, 1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0,
-    0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0,
-    0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0,
-    0, 1, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0,
-    0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
-    0, 0, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 0, 0,
-    0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-impl DisplayBoxed {
-    pub fn draw_default_display<'a>(
-        &mut self,
-        style: MonoTextStyle<'a, BinaryColor>,
-    ) -> anyhow::Result<()> {
-        self.draw_default_battery_percentage()?;
-        //Circle top
-        Circle::new(Point::new(55, 2), 40)
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
-            )
-            .draw(self)?;
-        // Circle buttom
-        Circle::new(Point::new(55, 86), 40)
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
-            )
-            .draw(self)?;
+        let epd = match panel::Epd::new(&mut driver, cs, busy, dc, rst, &mut delay::Ets) {
+            std::result::Result::Ok(epd) => epd,
+            Err(e) => return Err(anyhow::Error::new(e)),
+        };
 
-        // Circle left
-        Circle::new(Point::new(13, 44), 40)
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(2)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
+        let mut dis_boxed = DisplayBoxed::new();
+        dis_boxed.clear(BinaryColor::Off)?;
+        return Ok((dis_boxed, Panel::new(epd, driver)));
+    }
+}
 
-        //Circle right
-        Circle::new(Point::new(97, 44), 40)
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(2)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
-        // Solids for the icons
-        self.fill_solid(
-            &Rectangle::new(Point::new(66, 0), Size::new(18, 15)),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(Point::new(66, 84), Size::new(18, 15)),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(Point::new(24, 42), Size::new(18, 15)),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(Point::new(108, 42), Size::new(18, 15)),
-            BinaryColor::Off,
-        )?;
+#[cfg(target_os = "espidf")]
+pub use driver::init_display;
 
-        // icons
-        HOUSE_PATTERN
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 66 + (idx % 18);
-                let y = 0 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        LIGHTNING_BOLT_PATTERN
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 108 + (idx % 18);
-                let y = 43 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        BATTERY_PATTERN
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 66 + (idx % 18);
-                let y = 84 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        Line::new(Point::new(66 + 12, 85), Point::new(71, 96))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(1)
-                    .build(),
-            )
-            .draw(self)?;
+fn condition_icon(condition: Condition) -> &'static Bitmap {
+    match condition {
+        Condition::Clear => &icons::SUN,
+        Condition::PartlyCloudy => &icons::PARTLY_CLOUDY,
+        Condition::Overcast => &icons::CLOUD,
+        Condition::Rain => &icons::RAINDROPS,
+        Condition::Showers => &icons::SHOWERS,
+        Condition::Thunderstorm => &icons::THUNDERSTORM,
+        Condition::Snow => &icons::SNOW,
+    }
+}
 
-        SUN_PATTERN
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 24 + (idx % 18);
-                let y = 42 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        Line::new(Point::new(149, 0), Point::new(149, 128))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
-            )
-            .draw(self)?;
-        Line::new(Point::new(103, 0), Point::new(103, 20))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
-            )
-            .draw(self)?;
-        Line::new(Point::new(103, 20), Point::new(149, 20))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
-            )
-            .draw(self)?;
+// the lines between the circles, positions are relative to the center region
+struct Connections {
+    layout: Layout,
+    active: Vec<ConnectionDirection>,
+}
 
-        Text::new(
-            "%",
-            Point::new(71, 120),
-            MonoTextStyleBuilder::new()
-                .font(&embedded_graphics::mono_font::ascii::FONT_9X15)
-                .text_color(BinaryColor::On)
-                .build(),
+impl Connections {
+    fn line<D>(
+        &self,
+        target: &mut D,
+        start: (i32, i32),
+        end: (i32, i32),
+        width: u32,
+    ) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        Line::new(
+            self.layout.point(54 + start.0, 43 + start.1),
+            self.layout.point(54 + end.0, 43 + end.1),
         )
-        .draw(self)?;
-
-        let pos = [(65, 23), (22, 65), (107, 65)];
-        pos.iter().try_for_each(|pos| -> anyhow::Result<()> {
-            Text::new(
-                "kW",
-                Point::new(pos.0 + 5, pos.1 + 11),
-                MonoTextStyleBuilder::new()
-                    .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
-                    .text_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
-            Ok(())
-        })?;
-
-        Text::new(
-            "%",
-            Point::new(71, 120),
-            MonoTextStyleBuilder::new()
-                .font(&embedded_graphics::mono_font::ascii::FONT_9X15)
-                .text_color(BinaryColor::On)
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .stroke_color(BinaryColor::On)
+                .stroke_width(self.layout.length(width))
                 .build(),
         )
-        .draw(self)?;
-        self.draw_text(style, "0.00", "-0.00", "0.00", "-0.00", "0:00PM")?;
-        self.draw_default_weather()?;
-        self.draw_default_total()?;
+        .draw(target)?;
         Ok(())
     }
-    pub fn draw_text<'a>(
-        &mut self,
-        style: MonoTextStyle<'a, BinaryColor>,
-        circle_top: &'a str,
-        circle_bottom: &'a str,
-        circle_left: &'a str,
-        circle_right: &'a str,
-        update: &'a str,
-    ) -> anyhow::Result<()> {
-        // Circle top
-        // normally we have 3 digits
-        if circle_top.len() == 1 {
-            Text::new(circle_top, Point::new(74, 23), style).draw(self)?;
-        } else {
-            Text::new(circle_top, Point::new(65, 23), style).draw(self)?;
-        }
-
-        // Circle bottom
-        if circle_bottom.len() == 2 {
-            Text::new(circle_bottom, Point::new(69, 107), style).draw(self)?;
-        } else if circle_bottom.len() == 4 {
-            Text::new(circle_bottom, Point::new(64, 107), style).draw(self)?;
-        } else {
-            Text::new(circle_bottom, Point::new(60, 107), style).draw(self)?;
-        }
-
-        // Circle left
-        if circle_left.len() == 1 {
-            Text::new(circle_left, Point::new(31, 65), style).draw(self)?;
-        } else {
-            Text::new(circle_left, Point::new(22, 65), style).draw(self)?;
-        }
+}
 
-        // Circle right
-        if circle_right.len() == 2 {
-            Text::new(circle_right, Point::new(111, 65), style).draw(self)?;
-        } else {
-            Text::new(circle_right, Point::new(103, 65), style).draw(self)?;
+impl Widget for Connections {
+    fn bounds(&self) -> Rectangle {
+        self.layout.rect(54, 43, 42, 41)
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        for connection in self.active.iter() {
+            // the line from the middle and the two strokes of the arrow head
+            let (line, arrow, head) = match *connection {
+                ConnectionDirection::Top(arrow) => (
+                    ((21, 21), (21, 1)),
+                    arrow,
+                    [((21, 1), (28, 8)), ((20, 1), (13, 8))],
+                ),
+                ConnectionDirection::Left(arrow) => (
+                    ((20, 20), (1, 20)),
+                    arrow,
+                    [((1, 21), (8, 28)), ((1, 20), (8, 13))],
+                ),
+                ConnectionDirection::Right(arrow) => (
+                    ((20, 21), (40, 21)),
+                    arrow,
+                    [((40, 20), (33, 13)), ((40, 21), (33, 28))],
+                ),
+                ConnectionDirection::Bottom(arrow) => (
+                    ((20, 21), (20, 39)),
+                    arrow,
+                    [((20, 39), (13, 32)), ((21, 39), (28, 32))],
+                ),
+            };
+            self.line(target, line.0, line.1, 2)?;
+            if arrow {
+                for stroke in head {
+                    self.line(target, stroke.0, stroke.1, 1)?;
+                }
+            }
         }
+        Ok(())
+    }
+}
 
-        // Time
-        if update.len() == 6 {
-            Text::new(update, Point::new(110, 10), style).draw(self)?;
-        } else {
-            Text::new(update, Point::new(107, 10), style).draw(self)?;
-        }
+// "as of" or "restored" with a time, inverted so it stands out
+struct Banner {
+    title: Label,
+    time: Label,
+}
 
-        Ok(())
+impl Widget for Banner {
+    fn bounds(&self) -> Rectangle {
+        widget::envelope(&self.title.bounds(), &self.time.bounds())
     }
-    pub fn clear_text(&mut self) -> anyhow::Result<()> {
-        self.fill_solid(
-            &Rectangle::new(
-                Point::new(65, 15),
-                embedded_graphics::prelude::Size::new(25, 10),
-            ),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(
-                Point::new(60, 99),
-                embedded_graphics::prelude::Size::new(30, 10),
-            ),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(
-                Point::new(22, 57),
-                embedded_graphics::prelude::Size::new(25, 10),
-            ),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(
-                Point::new(102, 57),
-                embedded_graphics::prelude::Size::new(30, 10),
-            ),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(
-                Point::new(105, 1),
-                embedded_graphics::prelude::Size::new(42, 18),
-            ),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(Point::new(54, 43), Size::new(42, 41)),
-            BinaryColor::Off,
-        )?;
-        return Ok(());
+    fn role(&self) -> ColorRole {
+        ColorRole::Highlight
     }
-    pub fn display_error_message<'a>(
-        &mut self,
-        message: &str,
-        style: MonoTextStyle<'a, BinaryColor>,
-    ) -> anyhow::Result<()> {
-        Text::new(message, Point::new(58, 100), style).draw(self)?;
-        Ok(())
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        self.title.draw(target)?;
+        self.time.draw(target)
     }
-    pub fn draw_connections(&mut self, connection: ConnectionDirection) -> anyhow::Result<()> {
-        match connection {
-            ConnectionDirection::Top(arr) => {
-                // Line middle to top circle
-                Line::new(Point::new(75, 64), Point::new(75, 44))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_color(BinaryColor::On)
-                            .stroke_width(2)
-                            .build(),
-                    )
-                    .draw(self)?;
-                match arr {
-                    true => {
-                        self.draw_arrow(ArrowDirection::Up)?;
-                    }
-                    false => (),
-                }
-            }
-            ConnectionDirection::Left(arr) => {
-                //Line middle to left circle
-                Line::new(Point::new(74, 63), Point::new(55, 63))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_color(BinaryColor::On)
-                            .stroke_width(2)
-                            .build(),
-                    )
-                    .draw(self)?;
-                match arr {
-                    true => {
-                        self.draw_arrow(ArrowDirection::Left)?;
-                    }
-                    false => (),
-                }
-            }
-            ConnectionDirection::Right(arr) => {
-                // Line middle to right circle
-                Line::new(Point::new(74, 64), Point::new(94, 64))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_color(BinaryColor::On)
-                            .stroke_width(2)
-                            .build(),
-                    )
-                    .draw(self)?;
-                match arr {
-                    true => {
-                        self.draw_arrow(ArrowDirection::Right)?;
-                    }
-                    false => (),
-                }
-            }
-            ConnectionDirection::Bottom(arr) => {
-                // Line middle to bottom circle
-                Line::new(Point::new(74, 64), Point::new(74, 82))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_color(BinaryColor::On)
-                            .stroke_width(2)
-                            .build(),
-                    )
-                    .draw(self)?;
+}
 
-                match arr {
-                    true => {
-                        self.draw_arrow(ArrowDirection::Down)?;
-                    }
-                    false => (),
-                }
-            }
-        }
+// the active charging states as inverted tags below each other, each as wide as its text
+struct ChargingTags {
+    // the largest first tag, the second one is moved down by the spacing
+    tag: Rectangle,
+    spacing: i32,
+    font: &'static MonoFont<'static>,
+    padding: u32,
+    labels: Vec<String>,
+}
 
-        Ok(())
+impl Widget for ChargingTags {
+    fn bounds(&self) -> Rectangle {
+        let mut bounds = self.tag;
+        bounds.size.height += self.spacing as u32;
+        bounds
     }
-    pub fn set_connected(&mut self) -> anyhow::Result<()> {
-        Line::new(Point::new(0, 118), Point::new(40, 118))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
-        Line::new(Point::new(40, 118), Point::new(40, 128))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
-        Text::new(
-            "Connected",
-            Point::new(2, 125),
-            MonoTextStyleBuilder::new()
-                .font(&embedded_graphics::mono_font::ascii::FONT_4X6)
-                .text_color(BinaryColor::On)
-                .build(),
-        )
-        .draw(self)?;
-        Ok(())
-    }
-    fn draw_arrow(&mut self, direction: ArrowDirection) -> anyhow::Result<()> {
-        match direction {
-            ArrowDirection::Up => {
-                // Arrow up
-                Line::new(Point::new(75, 44), Point::new(82, 51))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                Line::new(Point::new(74, 44), Point::new(67, 51))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                // Arrow up end
-            }
-            ArrowDirection::Right => {
-                // Arrow right
-                Line::new(Point::new(94, 63), Point::new(87, 56))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                Line::new(Point::new(94, 64), Point::new(87, 71))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                //arrow right end
-            }
-            ArrowDirection::Down => {
-                // Arrow down
-                Line::new(Point::new(74, 82), Point::new(67, 75))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                Line::new(Point::new(75, 82), Point::new(82, 75))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                //arrow down end
-            }
-            ArrowDirection::Left => {
-                // Arrow left
-                Line::new(Point::new(55, 64), Point::new(62, 71))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                Line::new(Point::new(55, 63), Point::new(62, 56))
-                    .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_width(1)
-                            .stroke_color(BinaryColor::On)
-                            .build(),
-                    )
-                    .draw(self)?;
-                //arrow left end
-            }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        let width = self.tag.size.width.saturating_sub(2 * self.padding);
+        for (idx, label) in self.labels.iter().take(2).enumerate() {
+            let (font, text) = widget::fit(label, width, &[self.font]);
+            let tag = Rectangle::new(
+                self.tag.top_left + Point::new(0, idx as i32 * self.spacing),
+                Size::new(
+                    widget::text_width(&text, font) + 2 * self.padding,
+                    self.tag.size.height,
+                ),
+            );
+            Label::new(tag, font)
+                .aligned(Alignment::Center)
+                .inverted()
+                .with_text(&text)
+                .draw(target)?;
         }
         Ok(())
     }
-    pub fn update_battery_percentage(&mut self, percentage: &str) -> anyhow::Result<()> {
-        self.fill_solid(
-            &Rectangle::new(Point::new(1, 1), Size::new(28, 12)),
-            BinaryColor::Off,
-        )?;
-        if percentage.len() > 3 || percentage.len() < 1 {
-            return Err(anyhow!("errro input sequence too long"));
-        }
-        let offset = {
-            if percentage.len() == 3 {
-                0
-            } else if percentage.len() == 2 {
-                5
-            } else {
-                10
-            }
+}
+
+// self sufficiency and self consumption, the now and day values are right aligned
+struct MetricsTable {
+    bounds: Rectangle,
+    header: [Label; 3],
+    // label, now and day of each metric
+    rows: [[Label; 3]; 2],
+}
+
+impl MetricsTable {
+    fn new(layout: Layout) -> Self {
+        let header = |x: i32, width: u32, text: &str| {
+            Label::new(
+                layout.rect(x, 89, width, 7),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+            )
+            .with_text(text)
         };
-        Text::new(
-            &format!("{percentage}%"),
-            Point::new(3 + offset, 10),
-            MonoTextStyleBuilder::new()
-                .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
-                .text_color(BinaryColor::On)
-                .build(),
-        )
-        .draw(self)?;
+        let row = |y: i32| {
+            let cell = |x: i32, width: u32, alignment: Alignment| {
+                Label::new(
+                    layout.rect(x, y, width, 9),
+                    layout.font(&embedded_graphics::mono_font::ascii::FONT_5X8),
+                )
+                .aligned(alignment)
+                .shrink_to(layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6))
+            };
+            [
+                cell(1, 16, Alignment::Left),
+                cell(17, 16, Alignment::Right),
+                cell(37, 16, Alignment::Right),
+            ]
+        };
+        MetricsTable {
+            bounds: layout.rect(0, 87, 53, 30),
+            header: [
+                header(1, 16, "%"),
+                header(18, 18, "now"),
+                header(38, 15, "day"),
+            ],
+            rows: [row(97), row(107)],
+        }
+    }
+}
 
+impl Widget for MetricsTable {
+    fn bounds(&self) -> Rectangle {
+        self.bounds
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        for label in self.header.iter().chain(self.rows.iter().flatten()) {
+            label.draw(target)?;
+        }
         Ok(())
     }
-    fn draw_default_battery_percentage(&mut self) -> anyhow::Result<()> {
-        Line::new(Point::new(30, 0), Point::new(30, 15))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
-            )
-            .draw(self)?;
+}
 
-        Line::new(Point::new(0, 15), Point::new(30, 15))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
+// one hour of the forecast, values are right aligned so the decimal points line up
+struct WeatherColumn {
+    bounds: Rectangle,
+    rain: Label,
+    // takes the row of the cloud cover
+    condition: Icon,
+    uv: Label,
+    temp: Label,
+    hour: Label,
+}
+
+impl WeatherColumn {
+    fn new(x: i32, layout: Layout) -> Self {
+        let value = |y: i32| {
+            Label::new(
+                layout.rect(x, y, 26, 8),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_5X8),
             )
-            .draw(self)?;
-        Text::new(
-            "100%",
-            Point::new(3, 10),
-            MonoTextStyleBuilder::new()
-                .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
-                .text_color(BinaryColor::On)
-                .build(),
-        )
-        .draw(self)?;
-        Ok(())
+            .aligned(Alignment::Right)
+            .shrink_to(layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6))
+        };
+        WeatherColumn {
+            bounds: layout.rect(x, 18, 26, 51),
+            rain: value(20).with_text("0.0"),
+            condition: Icon::new(layout.point(x + 4, 28), &icons::SUN).scaled(layout.icon_scale()),
+            uv: value(43).with_text("0.0"),
+            temp: value(55).with_text("10.0"),
+            hour: Label::new(
+                layout.rect(x, 63, 26, 6),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+            )
+            .aligned(Alignment::Center)
+            .with_text("00:00"),
+        }
     }
-    pub fn update_sun_data(&mut self, sunrise: &str, sunset: &str) -> anyhow::Result<()> {
-        let style = MonoTextStyleBuilder::new()
-            .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
-            .text_color(BinaryColor::On)
-            .build();
-        if sunset.len() > 5 || sunrise.len() > 5 {
-            return Err(anyhow!("error input to long"));
+}
+
+impl Widget for WeatherColumn {
+    fn bounds(&self) -> Rectangle {
+        self.bounds
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        for label in [&self.rain, &self.uv, &self.temp, &self.hour] {
+            label.draw(target)?;
         }
+        self.condition.draw(target)
+    }
+}
 
-        // clears both text areas
-        self.fill_solid(
-            &Rectangle::new(Point::new(190, 3), Size::new(30, 12)),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(Point::new(260, 3), Size::new(30, 12)),
-            BinaryColor::Off,
-        )?;
+// ordered dithering, a pixel of the shading is set when its entry is below the level
+const BAYER: [u8; 16] = [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5];
+// rain in mm that fills the lower half of the chart, more rain is scaled down
+const FULL_RAIN: f32 = 5.0;
 
-        Text::new(sunrise, Point::new(190, 12), style).draw(self)?;
+// the hourly forecast as a temperature curve over rain bars, the cloud cover shades the top
+struct ForecastChart {
+    bounds: Rectangle,
+    font: &'static MonoFont<'static>,
+    // size of a plotted pixel
+    pixel: u32,
+    // missing values are NaN
+    temperature: Vec<f32>,
+    rain: Vec<f32>,
+    cloud: Vec<f32>,
+    hours: Vec<String>,
+}
 
-        Text::new(sunset, Point::new(260, 12), style).draw(self)?;
+impl ForecastChart {
+    fn fill<D>(&self, target: &mut D, points: impl Iterator<Item = Point>) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        let size = Size::new(self.pixel, self.pixel);
+        for point in points {
+            target.fill_solid(&Rectangle::new(point, size), BinaryColor::On)?;
+        }
         Ok(())
     }
+    // the grid of plotted pixels inside an area
+    fn grid(&self, area: Rectangle) -> impl Iterator<Item = Point> {
+        let pixel = self.pixel as usize;
+        let (left, top) = (area.top_left.x, area.top_left.y);
+        let (right, bottom) = (left + area.size.width as i32, top + area.size.height as i32);
+        (top..bottom)
+            .step_by(pixel)
+            .flat_map(move |y| (left..right).step_by(pixel).map(move |x| Point::new(x, y)))
+    }
+}
 
-    pub fn draw_default_weather(&mut self) -> anyhow::Result<()> {
-        // first split display on the right into 2;
+impl Widget for ForecastChart {
+    fn bounds(&self) -> Rectangle {
+        self.bounds
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        let slots = self.hours.len().max(self.temperature.len());
+        if slots == 0 {
+            return Ok(());
+        }
+        let pixel = self.pixel as i32;
+        let font_height = self.font.character_size.height as i32;
+        let (left, top) = (self.bounds.top_left.x, self.bounds.top_left.y);
+        let slot = (self.bounds.size.width as i32 / slots as i32).max(1);
+        // the hour labels are below the plot
+        let bottom = top + self.bounds.size.height as i32 - font_height - 1;
+        let height = bottom - top;
+        let band = height / 4;
 
-        Line::new(Point::new(149, 70), Point::new(296, 70))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_color(BinaryColor::On)
-                    .stroke_width(2)
-                    .build(),
-            )
-            .draw(self)?;
-        SUN_PATTERN
+        for (idx, cloud) in self.cloud.iter().enumerate() {
+            let level = (cloud.clamp(0.0, 100.0) / 100.0 * 16.0).round() as u8;
+            let area = Rectangle::new(
+                Point::new(left + idx as i32 * slot, top),
+                Size::new(slot as u32, band as u32),
+            );
+            let shaded = self.grid(area).filter(|point| {
+                let (x, y) = ((point.x / pixel) as usize, (point.y / pixel) as usize);
+                BAYER[(y % 4) * 4 + x % 4] < level
+            });
+            self.fill(target, shaded)?;
+        }
+
+        let rain_scale = self.rain.iter().copied().fold(FULL_RAIN, f32::max);
+        let rainy = self
+            .rain
             .iter()
             .enumerate()
-            .map(|(idx, num)| {
-                let x = 155 + (idx % 18);
-                let y = 2 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-
-        // arrow up
-        self.draw_arrow_simple(SimpleArrowDirection::Up, (178, 3))?;
+            .filter(|(_, rain)| **rain > 0.0);
+        for (idx, rain) in rainy {
+            let bar = ((rain / rain_scale * (height / 2) as f32).round() as i32).max(pixel);
+            let area = Rectangle::new(
+                Point::new(left + idx as i32 * slot + pixel, bottom - bar),
+                Size::new((slot - 2 * pixel).max(pixel) as u32, bar as u32),
+            );
+            // checkered so the curve stays visible on top of the bars
+            let checkered = self
+                .grid(area)
+                .filter(|point| (point.x / pixel + point.y / pixel) % 2 == 0);
+            self.fill(target, checkered)?;
+        }
 
-        SUN_PATTERN
+        let (min, max) = self
+            .temperature
             .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 225 + (idx % 18);
-                let y = 2 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        // arrow down
-        self.draw_arrow_simple(SimpleArrowDirection::Down, (248, 3))?;
+            .filter(|value| value.is_finite())
+            .fold((f32::MAX, f32::MIN), |(min, max), value| {
+                (min.min(*value), max.max(*value))
+            });
+        if min <= max {
+            let max = match max > min {
+                true => max,
+                false => min + 1.0,
+            };
+            // between the cloud band and the label of the lowest value
+            let (curve_top, curve_bottom) = (top + band + pixel, bottom - font_height - pixel);
+            let y = |value: f32| {
+                curve_top
+                    + ((max - value) / (max - min) * (curve_bottom - curve_top) as f32).round()
+                        as i32
+            };
+            let points: Vec<Option<Point>> = self
+                .temperature
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| {
+                    value
+                        .is_finite()
+                        .then(|| Point::new(left + idx as i32 * slot + slot / 2, y(*value)))
+                })
+                .collect();
+            for pair in points.windows(2) {
+                if let [Some(start), Some(end)] = pair {
+                    self.fill(target, Line::new(*start, *end).points())?;
+                }
+            }
+            // single values between gaps
+            for (idx, point) in points.iter().enumerate() {
+                let before = idx > 0 && points[idx - 1].is_some();
+                let after = points.get(idx + 1).is_some_and(|point| point.is_some());
+                if let (Some(point), false, false) = (point, before, after) {
+                    self.fill(target, core::iter::once(*point))?;
+                }
+            }
+            let style = MonoTextStyleBuilder::new()
+                .font(self.font)
+                .text_color(BinaryColor::On)
+                .background_color(BinaryColor::Off)
+                .build();
+            let labels = [
+                (max, curve_top, Baseline::Top),
+                (min, bottom, Baseline::Bottom),
+            ];
+            for (value, y, baseline) in labels {
+                Text::with_text_style(
+                    &format!("{:.0}", value),
+                    Point::new(left, y),
+                    style,
+                    TextStyleBuilder::new().baseline(baseline).build(),
+                )
+                .draw(target)?;
+            }
+        }
+
         let style = MonoTextStyleBuilder::new()
-            .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
+            .font(self.font)
             .text_color(BinaryColor::On)
             .build();
-
-        // sunset and sunrise values
-        Text::new("00.00", Point::new(190, 12), style).draw(self)?;
-        Text::new("00.00", Point::new(260, 12), style).draw(self)?;
-
-        // other descriptors
-        RAINDROPS
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 155 + (idx % 18);
-                let y = 15 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        CLOUD
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 155 + (idx % 18);
-                let y = 28 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-
-        Text::new("UV", Point::new(160, 50), style).draw(self)?;
-        TEMP.iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 156 + (idx % 18);
-                let y = 52 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        let offsets = &[20, 50, 80, 110];
-        for x_offset in offsets.iter() {
-            self.draw_row_weather_data("0.0", "100.0", "0.0", "10.0", x_offset.clone())?;
-        }
-        Line::new(Point::new(203, 18), Point::new(203, 65))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
+        let label_width = self.hours.iter().fold(0, |width, hour| {
+            width.max(widget::text_width(hour, self.font) as i32)
+        });
+        // every n-th hour so the labels don't overlap
+        let step = ((label_width + self.font.character_size.width as i32) + slot - 1) / slot;
+        for (idx, hour) in self.hours.iter().enumerate().step_by(step.max(1) as usize) {
+            Text::with_text_style(
+                hour,
+                Point::new(left + idx as i32 * slot + slot / 2, bottom + 1),
+                style,
+                TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Top)
                     .build(),
             )
-            .draw(self)?;
+            .draw(target)?;
+        }
+        Ok(())
+    }
+}
 
-        Line::new(Point::new(232, 18), Point::new(232, 65))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
+// all widgets of the dashboard, moving an element only means changing it here
+struct Dashboard {
+    circles: [CircleFrame; 4],
+    separators: Vec<Separator>,
+    units: Vec<Label>,
+    // the battery percentage in the top left corner
+    soc: Label,
+    // the values in the circles
+    house: Label,
+    battery: Label,
+    pv: Label,
+    grid: Label,
+    clock: Label,
+    banner: Banner,
+    connections: Connections,
+    sun_icons: Vec<Icon>,
+    // the labels and separators of the forecast columns
+    weather_icons: Vec<Icon>,
+    totals_separator: Separator,
+    weather_separators: Vec<Separator>,
+    uv_label: Label,
+    sunrise: Label,
+    sunset: Label,
+    weather: [WeatherColumn; 4],
+    // replaces the columns when the forecast is shown as a chart
+    forecast: ForecastChart,
+    totals_icons: Vec<Icon>,
+    totals_separators: Vec<Separator>,
+    totals_title: Label,
+    // generated and consumption with their units
+    totals: [Label; 2],
+    totals_units: Vec<Label>,
+    // earnings and cost left of the totals icons, savings in the header
+    earnings: Label,
+    cost: Label,
+    savings: Label,
+    // below the battery percentage
+    status: Label,
+    // today and the total, left of the house circle
+    co2: [Label; 2],
+    metrics: MetricsTable,
+    // right of the battery circle
+    charging: ChargingTags,
+    estimate: [Label; 2],
+    // covers the totals on the chart page
+    chart: Sparkline,
+    // right of the totals title
+    totals_warning: Label,
+    // sunrise and sunset
+    sun_arrows: Vec<Separator>,
+    // bottom left, once the websocket is connected
+    connected: Label,
+    connected_box: Vec<Separator>,
+}
 
-        Line::new(Point::new(261, 18), Point::new(261, 65))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
+impl Dashboard {
+    fn new(layout: Layout) -> Self {
+        let icon_scale = layout.icon_scale();
+        let font = layout.font(&embedded_graphics::mono_font::ascii::FONT_6X10);
+        let small_font = layout.font(&embedded_graphics::mono_font::ascii::FONT_5X8);
+        let value = |x: i32, y: i32, width: u32| {
+            Label::new(layout.rect(x, y, width, 10), font)
+                .aligned(Alignment::Center)
+                .shrink_to(small_font)
+        };
+        // "12:05PM" only fits with the small font
+        let sun = |x: i32| {
+            Label::new(layout.rect(x, 3, 31, 12), font)
+                .shrink_to(layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6))
+                .with_text("00.00")
+        };
+        let total = |y: i32| {
+            Label::new(
+                layout.rect(204, y, 46, 15),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_9X15),
+            )
+            .shrink_to(font)
+            .with_text("00.00")
+        };
+        let total_unit = |y: i32| {
+            Label::new(
+                layout.rect(258, y, 20, 15),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_9X15),
+            )
+            .with_text("kW")
+        };
+        let money = |x: i32, y: i32, width: u32| {
+            Label::new(layout.rect(x, y, width, 14), small_font)
+                .shrink_to(layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6))
+        };
+        // a shaft with the head at the top or the bottom
+        let arrow = |x: i32, up: bool| {
+            let (tip, head) = match up {
+                true => (3, 9),
+                false => (14, 8),
+            };
+            let line = |start: Point, end: Point| Separator::new(start, end, layout.length(2));
+            [
+                line(layout.point(x, 3), layout.point(x, 14)),
+                line(layout.point(x, tip), layout.point(x - 6, head)),
+                line(layout.point(x, tip), layout.point(x + 6, head)),
+            ]
+        };
+        let co2 = |y: i32| {
+            Label::new(
+                layout.rect(1, y, 53, 6),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+            )
+        };
+        let estimate = |y: i32| {
+            Label::new(
+                layout.rect(99, y, 50, 7),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+            )
+        };
+        Dashboard {
+            circles: [
+                CircleFrame::new(
+                    layout.point(55, 2),
+                    layout.length(40),
+                    layout.point(0, 0).y,
+                    &icons::HOUSE,
+                )
+                .stroke(layout.length(2))
+                .icon_scaled(icon_scale),
+                CircleFrame::new(
+                    layout.point(55, 86),
+                    layout.length(40),
+                    layout.point(0, 84).y,
+                    &icons::BATTERY,
+                )
+                .stroke(layout.length(2))
+                .icon_scaled(icon_scale),
+                CircleFrame::new(
+                    layout.point(13, 44),
+                    layout.length(40),
+                    layout.point(0, 42).y,
+                    &icons::SUN,
+                )
+                .stroke(layout.length(2))
+                .icon_scaled(icon_scale),
+                CircleFrame::new(
+                    layout.point(97, 44),
+                    layout.length(40),
+                    layout.point(0, 43).y,
+                    &icons::LIGHTNING_BOLT,
+                )
+                .stroke(layout.length(2))
+                .icon_scaled(icon_scale),
+            ],
+            separators: vec![
+                // the bolt across the battery icon
+                Separator::new(layout.point(78, 85), layout.point(71, 96), layout.length(1)),
+                // left half and the clock box
+                Separator::new(
+                    layout.point(149, 0),
+                    layout.point(149, 128),
+                    layout.length(2),
+                ),
+                Separator::new(
+                    layout.point(103, 0),
+                    layout.point(103, 20),
+                    layout.length(2),
+                ),
+                Separator::new(
+                    layout.point(103, 20),
+                    layout.point(149, 20),
+                    layout.length(2),
+                ),
+                // the box around the battery percentage
+                Separator::new(layout.point(30, 0), layout.point(30, 15), layout.length(2)),
+                Separator::new(layout.point(0, 15), layout.point(30, 15), layout.length(2)),
+            ],
+            units: vec![
+                value(65, 26, 20).with_text("kW"),
+                value(22, 68, 20).with_text("kW"),
+                value(107, 68, 20).with_text("kW"),
+                Label::new(
+                    layout.rect(66, 109, 20, 15),
+                    layout.font(&embedded_graphics::mono_font::ascii::FONT_9X15),
+                )
+                .aligned(Alignment::Center)
+                .with_text("%"),
+            ],
+            soc: Label::new(layout.rect(1, 1, 28, 12), font)
+                .aligned(Alignment::Right)
+                .with_text("100%"),
+            house: value(60, 15, 31).with_text("0.00"),
+            battery: value(58, 99, 34).with_text("-0.00"),
+            pv: value(18, 57, 31).with_text("0.00"),
+            grid: value(101, 57, 32).with_text("-0.00"),
+            clock: Label::new(layout.rect(105, 1, 42, 18), font)
+                .aligned(Alignment::Center)
+                .shrink_to(small_font)
+                .with_text("0:00PM"),
+            banner: Banner {
+                title: Label::new(
+                    layout.rect(105, 23, 42, 7),
+                    layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+                )
+                .inverted(),
+                time: Label::new(layout.rect(105, 30, 42, 11), font)
+                    .shrink_to(layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6))
+                    .inverted(),
+            },
+            connections: Connections {
+                layout,
+                active: Vec::new(),
+            },
+            sun_icons: vec![
+                Icon::new(layout.point(155, 2), &icons::SUN).scaled(icon_scale),
+                Icon::new(layout.point(225, 2), &icons::SUN).scaled(icon_scale),
+            ],
+            weather_icons: vec![
+                Icon::new(layout.point(155, 15), &icons::RAINDROPS).scaled(icon_scale),
+                Icon::new(layout.point(156, 52), &icons::TEMP).scaled(icon_scale),
+            ],
+            totals_separator: Separator::new(
+                layout.point(149, 70),
+                layout.point(296, 70),
+                layout.length(2),
+            ),
+            weather_separators: vec![
+                Separator::new(
+                    layout.point(203, 18),
+                    layout.point(203, 65),
+                    layout.length(1),
+                ),
+                Separator::new(
+                    layout.point(232, 18),
+                    layout.point(232, 65),
+                    layout.length(1),
+                ),
+                Separator::new(
+                    layout.point(261, 18),
+                    layout.point(261, 65),
+                    layout.length(1),
+                ),
+            ],
+            uv_label: Label::new(layout.rect(159, 42, 14, 10), font).with_text("UV"),
+            sunrise: sun(189),
+            sunset: sun(259),
+            weather: [
+                WeatherColumn::new(175, layout),
+                WeatherColumn::new(205, layout),
+                WeatherColumn::new(235, layout),
+                WeatherColumn::new(265, layout),
+            ],
+            forecast: ForecastChart {
+                bounds: layout.rect(152, 17, 144, 51),
+                font: layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+                pixel: layout.length(1),
+                temperature: Vec::new(),
+                rain: Vec::new(),
+                cloud: Vec::new(),
+                hours: Vec::new(),
+            },
+            totals_icons: vec![
+                Icon::new(layout.point(180, 90), &icons::SUN).scaled(icon_scale),
+                Icon::new(layout.point(180, 110), &icons::HOUSE).scaled(icon_scale),
+            ],
+            totals_separators: vec![
+                Separator::new(
+                    layout.point(150, 86),
+                    layout.point(190, 86),
+                    layout.length(2),
+                ),
+                Separator::new(
+                    layout.point(190, 86),
+                    layout.point(190, 70),
+                    layout.length(2),
+                ),
+            ],
+            totals_title: Label::new(
+                layout.rect(153, 72, 36, 12),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_6X12),
             )
-            .draw(self)?;
+            .with_text("Total"),
+            totals: [total(89), total(109)],
+            totals_units: vec![total_unit(89), total_unit(109)],
+            earnings: money(151, 90, 28),
+            cost: money(151, 110, 28),
+            savings: money(194, 73, 88),
+            status: Label::new(layout.rect(1, 18, 52, 12), font)
+                .shrink_to(layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6)),
+            co2: [co2(30), co2(36)],
+            metrics: MetricsTable::new(layout),
+            charging: ChargingTags {
+                tag: layout.rect(100, 90, 47, 9),
+                spacing: layout.point(0, 11).y - layout.point(0, 0).y,
+                font: layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+                padding: layout.length(2),
+                labels: Vec::new(),
+            },
+            estimate: [estimate(113), estimate(120)],
+            chart: Sparkline::new(
+                layout.rect(151, 71, 145, 57),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+                "kW",
+            )
+            .with_series("pv", Stroke::Solid)
+            .with_series("house", Stroke::Dotted)
+            .pixel(layout.length(1))
+            .padding(layout.length(2)),
+            totals_warning: Label::new(layout.rect(283, 73, 12, 13), font)
+                .aligned(Alignment::Center)
+                .inverted()
+                .highlighted()
+                .with_text("!"),
+            sun_arrows: [arrow(178, true), arrow(248, false)]
+                .into_iter()
+                .flatten()
+                .collect(),
+            connected: Label::new(
+                layout.rect(1, 119, 38, 8),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6),
+            )
+            .with_text("Connected"),
+            connected_box: vec![
+                Separator::new(
+                    layout.point(0, 118),
+                    layout.point(40, 118),
+                    layout.length(1),
+                ),
+                Separator::new(
+                    layout.point(40, 118),
+                    layout.point(40, 128),
+                    layout.length(1),
+                ),
+            ],
+        }
+    }
+}
 
+impl Default for DisplayBoxed {
+    fn default() -> Self {
+        DisplayBoxed::new()
+    }
+}
+
+impl DisplayBoxed {
+    // the buffers of the selected panel, laid out for its size
+    pub fn new() -> Self {
+        let layout = Layout::new(panel::size());
+        let mut display = DisplayBoxed {
+            layers: Layers::new(),
+            layout,
+            dashboard: Dashboard::new(layout),
+            forecast_hours: None,
+        };
+        display.set_rotation(panel::ROTATION);
+        display
+    }
+    pub fn draw_default_display(&mut self) -> anyhow::Result<()> {
+        let dashboard = &self.dashboard;
+        self.layers.redraw(&dashboard.soc)?;
+        for circle in dashboard.circles.iter() {
+            self.layers.draw(circle)?;
+        }
+        for separator in dashboard.separators.iter() {
+            self.layers.draw(separator)?;
+        }
+        for label in dashboard.units.iter() {
+            self.layers.draw(label)?;
+        }
+        for label in [
+            &dashboard.house,
+            &dashboard.battery,
+            &dashboard.pv,
+            &dashboard.grid,
+            &dashboard.clock,
+        ] {
+            self.layers.redraw(label)?;
+        }
+        self.draw_default_weather()?;
+        self.draw_default_total()?;
         Ok(())
     }
-    fn draw_row_weather_data<'a>(
+    pub fn draw_text(
         &mut self,
-        rain: &'a str,
-        cloud: &'a str,
-        uv: &'a str,
-        temp: &'a str,
-        x_offset: i32,
+        circle_top: &str,
+        circle_bottom: &str,
+        circle_left: &str,
+        circle_right: &str,
+        update: &str,
     ) -> anyhow::Result<()> {
-        let style_2 = MonoTextStyleBuilder::new()
-            .font(&embedded_graphics::mono_font::ascii::FONT_5X8)
-            .text_color(BinaryColor::On)
-            .build();
-        if rain.len() == 3 {
-            Text::new(rain, Point::new(155 + x_offset + 10, 27), style_2).draw(self)?;
-        } else if rain.len() == 4 {
-            Text::new(rain, Point::new(155 + x_offset + 5, 27), style_2).draw(self)?;
-        } else {
-            Text::new(rain, Point::new(155 + x_offset, 27), style_2).draw(self)?;
+        let dashboard = &mut self.dashboard;
+        dashboard.house.set_text(circle_top);
+        dashboard.battery.set_text(circle_bottom);
+        dashboard.pv.set_text(circle_left);
+        dashboard.grid.set_text(circle_right);
+        dashboard.clock.set_text(update);
+        for label in [
+            &dashboard.house,
+            &dashboard.battery,
+            &dashboard.pv,
+            &dashboard.grid,
+            &dashboard.clock,
+        ] {
+            self.layers.redraw(label)?;
         }
-        if cloud.len() == 3 {
-            Text::new(cloud, Point::new(155 + x_offset + 10, 38), style_2).draw(self)?;
-        } else if cloud.len() == 4 {
-            Text::new(cloud, Point::new(155 + x_offset + 5, 38), style_2).draw(self)?;
-        } else {
-            Text::new(cloud, Point::new(155 + x_offset, 38), style_2).draw(self)?;
+        Ok(())
+    }
+    pub fn mark_stale(&mut self, as_of: &str) -> anyhow::Result<()> {
+        self.draw_banner("as of", as_of)
+    }
+    // values restored from flash after a reboot
+    pub fn mark_restored(&mut self, saved_at: &str) -> anyhow::Result<()> {
+        self.draw_banner("restored", saved_at)
+    }
+    fn draw_banner(&mut self, title: &str, time: &str) -> anyhow::Result<()> {
+        let banner = &mut self.dashboard.banner;
+        banner.title.set_text(title);
+        banner.time.set_text(time);
+        self.layers.redraw(banner)
+    }
+    // removes the "as of" or "restored" banner once fresh values arrive
+    pub fn hide_banner(&mut self) -> anyhow::Result<()> {
+        self.layers.clear_region(&self.dashboard.banner.bounds())
+    }
+    // grid import and a low battery stand out, the values are redrawn with the next draw_text
+    pub fn highlight_values(&mut self, grid_import: bool, low_battery: bool) {
+        let role = |highlight: bool| match highlight {
+            true => ColorRole::Highlight,
+            false => ColorRole::Foreground,
+        };
+        self.dashboard.grid.set_role(role(grid_import));
+        self.dashboard.battery.set_role(role(low_battery));
+    }
+    // what is sent to the panel, the red layer only exists on three color panels.
+    // the dirty regions are handed over with it, the next frame only has what is drawn after this
+    pub fn frame(&mut self) -> panel::Frame<'_> {
+        let dirty = self.layers.dirty.take();
+        panel::Frame {
+            dirty,
+            black: self.layers.black.buffer(),
+            chromatic: self
+                .layers
+                .chromatic
+                .as_ref()
+                .map(|chromatic| chromatic.buffer()),
         }
-        if uv.len() == 3 {
-            Text::new(uv, Point::new(155 + x_offset + 10, 50), style_2).draw(self)?;
-        } else if uv.len() == 4 {
-            Text::new(uv, Point::new(155 + x_offset + 5, 50), style_2).draw(self)?;
-        } else {
-            Text::new(uv, Point::new(155 + x_offset, 50), style_2).draw(self)?;
+    }
+    // redraws only the time in the top right corner
+    pub fn draw_clock(&mut self, time: &str) -> anyhow::Result<()> {
+        self.dashboard.clock.set_text(time);
+        self.layers.redraw(&self.dashboard.clock)
+    }
+    pub fn draw_status(&mut self, label: &str) -> anyhow::Result<()> {
+        self.dashboard.status.set_text(label);
+        self.layers.redraw(&self.dashboard.status)
+    }
+    // inverted labels right of the battery circle, only the active ones are shown
+    pub fn draw_charging_info(&mut self, labels: &[&str]) -> anyhow::Result<()> {
+        let charging = &mut self.dashboard.charging;
+        charging.labels.clear();
+        charging
+            .labels
+            .extend(labels.iter().map(|label| label.to_string()));
+        self.layers.redraw(&self.dashboard.charging)
+    }
+    // self sufficiency and self consumption in the bottom left corner, rows are (label, now, day)
+    pub fn draw_metrics(&mut self, rows: [(&str, &str, &str); 2]) -> anyhow::Result<()> {
+        let metrics = &mut self.dashboard.metrics;
+        for (cells, (label, now, day)) in metrics.rows.iter_mut().zip(rows.iter()) {
+            for (cell, text) in cells.iter_mut().zip([label, now, day]) {
+                cell.set_text(text);
+            }
         }
-        if temp.len() == 3 {
-            Text::new(temp, Point::new(155 + x_offset + 10, 62), style_2).draw(self)?;
-        } else if temp.len() == 4 {
-            Text::new(temp, Point::new(155 + x_offset + 5, 62), style_2).draw(self)?;
-        } else {
-            Text::new(temp, Point::new(155 + x_offset, 62), style_2).draw(self)?;
+        self.layers.redraw(&self.dashboard.metrics)
+    }
+    // time until the battery is full or empty, right of the battery below the charging info
+    pub fn draw_battery_estimate(&mut self, first: &str, second: &str) -> anyhow::Result<()> {
+        let estimate = &mut self.dashboard.estimate;
+        estimate[0].set_text(first);
+        estimate[1].set_text(second);
+        for label in self.dashboard.estimate.iter() {
+            self.layers.redraw(label)?;
         }
-
         Ok(())
     }
-    pub fn update_weather_data(&mut self, weather_data: HourlyNew) -> anyhow::Result<()> {
-        let offsets = &[20, 50, 80, 110];
-        self.fill_solid(
-            &Rectangle::new(Point::new(172, 18), Size::new(130, 50)),
-            BinaryColor::Off,
-        )?;
-        for (idx, x_offset) in offsets.iter().enumerate() {
-            let rain = weather_data
-                .rain
-                .get(idx)
-                .ok_or(anyhow!("error missing data"))?;
-            let cloud = weather_data
-                .cloud_cover
-                .get(idx)
-                .ok_or(anyhow!("error missing data"))?;
-            let uv = weather_data
-                .uv_index
-                .get(idx)
-                .ok_or(anyhow!("error missing data"))?;
-            let temp = weather_data
-                .temperature_2_m
-                .get(idx)
-                .ok_or(anyhow!("error missing data"))?;
-
-            self.draw_row_weather_data(rain, cloud, uv, temp, x_offset.clone())?
+    // marks the totals when the server and the local counters disagree
+    pub fn draw_totals_warning(&mut self, show: bool) -> anyhow::Result<()> {
+        let warning = &self.dashboard.totals_warning;
+        self.layers.clear_region(&warning.bounds())?;
+        if show {
+            self.layers.draw(warning)?;
         }
-
-        // seperation lines
-        Line::new(Point::new(203, 18), Point::new(203, 65))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
+        Ok(())
+    }
+    // earnings and cost left of the generated and consumption icons, savings in the header
+    pub fn draw_costs(&mut self, earnings: &str, cost: &str, savings: &str) -> anyhow::Result<()> {
+        let dashboard = &mut self.dashboard;
+        dashboard.earnings.set_text(earnings);
+        dashboard.cost.set_text(cost);
+        dashboard.savings.set_text(savings);
+        for label in [&dashboard.earnings, &dashboard.cost, &dashboard.savings] {
+            self.layers.redraw(label)?;
+        }
+        Ok(())
+    }
+    // avoided emissions of today and since the first start, between the status and the left circle
+    pub fn draw_co2(&mut self, today: &str, total: &str) -> anyhow::Result<()> {
+        let co2 = &mut self.dashboard.co2;
+        co2[0].set_text(&format!("CO2 {}", today));
+        co2[1].set_text(total);
+        for label in self.dashboard.co2.iter() {
+            self.layers.redraw(label)?;
+        }
+        Ok(())
+    }
+    // takes over the whole screen so faults are noticed from across the room
+    pub fn display_warning_screen(
+        &mut self,
+        title: &str,
+        detail: &str,
+        fault: bool,
+    ) -> anyhow::Result<()> {
+        let layout = self.layout;
+        let thick = layout.length(3);
+        let frame = [(2, 2), (294, 2), (294, 126), (2, 126)];
+        // as lines, a thick triangle overflows in embedded-graphics on the larger panels
+        let sign = [(20, 100), (55, 28), (90, 100)];
+        let lines = |corners: &[(i32, i32)]| -> Vec<Separator> {
+            corners
+                .iter()
+                .zip(corners.iter().cycle().skip(1))
+                .map(|(start, end)| {
+                    Separator::new(
+                        layout.point(start.0, start.1),
+                        layout.point(end.0, end.1),
+                        thick,
+                    )
+                })
+                .collect()
+        };
+        for separator in lines(&frame) {
+            self.layers.draw(&separator)?;
+        }
+        for separator in lines(&sign) {
+            self.layers.draw(&separator.highlighted())?;
+        }
+        let hint = match fault {
+            true => "Check the system or call service",
+            false => "Maintenance in progress",
+        };
+        let font = |font: &'static MonoFont<'static>| layout.font(font);
+        let labels = [
+            Label::new(
+                layout.rect(45, 70, 20, 26),
+                font(&embedded_graphics::mono_font::ascii::FONT_10X20),
             )
-            .draw(self)?;
-
-        Line::new(Point::new(232, 18), Point::new(232, 65))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
+            .aligned(Alignment::Center)
+            .highlighted()
+            .with_text("!"),
+            Label::new(
+                layout.rect(104, 35, 187, 20),
+                font(&embedded_graphics::mono_font::ascii::FONT_10X20),
             )
-            .draw(self)?;
-        Line::new(Point::new(261, 18), Point::new(261, 65))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(1)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
+            .shrink_to(font(&embedded_graphics::mono_font::ascii::FONT_6X10))
+            .with_text(title),
+            // the longest senec names only fit with the smaller font
+            Label::new(
+                layout.rect(104, 63, 187, 10),
+                font(&embedded_graphics::mono_font::ascii::FONT_6X10),
             )
-            .draw(self)?;
-
+            .shrink_to(font(&embedded_graphics::mono_font::ascii::FONT_5X8))
+            .with_text(detail),
+            Label::new(
+                layout.rect(104, 89, 187, 8),
+                font(&embedded_graphics::mono_font::ascii::FONT_5X8),
+            )
+            .with_text(hint),
+        ];
+        for label in labels.iter() {
+            self.layers.draw(label)?;
+        }
         Ok(())
     }
-    fn draw_arrow_simple(
+    // a table with a label and two values per row, the records and the periods of energy
+    pub fn display_records_page(
         &mut self,
-        direction: SimpleArrowDirection,
-        startpos: (i32, i32),
+        title: &str,
+        columns: (&str, &str),
+        rows: &[(&str, &str, &str)],
     ) -> anyhow::Result<()> {
-        match direction {
-            SimpleArrowDirection::Up => {
-                Line::new(
-                    Point::new(startpos.0, startpos.1),
-                    Point::new(startpos.0, startpos.1 + 11),
-                )
-                .into_styled(
-                    PrimitiveStyleBuilder::new()
-                        .stroke_width(2)
-                        .stroke_color(BinaryColor::On)
-                        .build(),
-                )
-                .draw(self)?;
-                Line::new(
-                    Point::new(startpos.0, startpos.1),
-                    Point::new(startpos.0 + 6, startpos.1 + 6),
-                )
-                .into_styled(
-                    PrimitiveStyleBuilder::new()
-                        .stroke_width(2)
-                        .stroke_color(BinaryColor::On)
-                        .build(),
-                )
-                .draw(self)?;
-                Line::new(
-                    Point::new(startpos.0, startpos.1),
-                    Point::new(startpos.0 - 6, startpos.1 + 6),
-                )
-                .into_styled(
-                    PrimitiveStyleBuilder::new()
-                        .stroke_width(2)
-                        .stroke_color(BinaryColor::On)
-                        .build(),
-                )
-                .draw(self)?;
-                Pixel(Point::new(startpos.0 - 6, startpos.1 + 6), BinaryColor::Off).draw(self)?;
-            }
-            SimpleArrowDirection::Down => {
-                Line::new(
-                    Point::new(startpos.0, startpos.1),
-                    Point::new(startpos.0, startpos.1 + 11),
-                )
-                .into_styled(
-                    PrimitiveStyleBuilder::new()
-                        .stroke_width(2)
-                        .stroke_color(BinaryColor::On)
-                        .build(),
-                )
-                .draw(self)?;
-                Line::new(
-                    Point::new(startpos.0 + 1, startpos.1 + 11),
-                    Point::new(startpos.0 - 5, startpos.1 + 11 - 6),
-                )
-                .into_styled(
-                    PrimitiveStyleBuilder::new()
-                        .stroke_width(2)
-                        .stroke_color(BinaryColor::On)
-                        .build(),
-                )
-                .draw(self)?;
-                Line::new(
-                    Point::new(startpos.0 + 1, startpos.1 + 11),
-                    Point::new(startpos.0 + 7, startpos.1 + 11 - 6),
-                )
-                .into_styled(
-                    PrimitiveStyleBuilder::new()
-                        .stroke_width(2)
-                        .stroke_color(BinaryColor::On)
-                        .build(),
-                )
-                .draw(self)?;
-                Pixel(
-                    Point::new(startpos.0 + 7, startpos.1 + 11 - 6),
-                    BinaryColor::Off,
-                )
-                .draw(self)?;
+        let layout = self.layout;
+        let font = layout.font(&embedded_graphics::mono_font::ascii::FONT_6X10);
+        let small_font = layout.font(&embedded_graphics::mono_font::ascii::FONT_4X6);
+        // the label and the two value columns
+        let cells = |y: i32, texts: [&str; 3]| -> Vec<Label> {
+            [(3, 72), (75, 94), (169, 126)]
+                .into_iter()
+                .zip(texts)
+                .map(|((x, width), text)| {
+                    Label::new(layout.rect(x, y, width, 11), font)
+                        .shrink_to(small_font)
+                        .with_text(text)
+                })
+                .collect()
+        };
+        self.layers.draw(
+            &Label::new(
+                layout.rect(3, 2, 290, 13),
+                layout.font(&embedded_graphics::mono_font::ascii::FONT_6X12),
+            )
+            .with_text(title),
+        )?;
+        for label in cells(19, ["", columns.0, columns.1]) {
+            self.layers.draw(&label)?;
+        }
+        self.layers.draw(&Separator::new(
+            layout.point(0, 31),
+            layout.point(296, 31),
+            layout.length(1),
+        ))?;
+        for (idx, (label, first, second)) in rows.iter().take(5).enumerate() {
+            for label in cells(36 + idx as i32 * 18, [label, first, second]) {
+                self.layers.draw(&label)?;
             }
         }
+        Ok(())
+    }
+    pub fn display_error_message<'a>(
+        &mut self,
+        message: &str,
+        style: MonoTextStyle<'a, BinaryColor>,
+    ) -> anyhow::Result<()> {
+        Text::new(message, self.layout.point(58, 100), style).draw(self)?;
+        Ok(())
+    }
+    // replaces the lines between the circles with the given ones
+    pub fn draw_connections(&mut self, connections: &[ConnectionDirection]) -> anyhow::Result<()> {
+        let widget = &mut self.dashboard.connections;
+        widget.active.clear();
+        widget.active.extend_from_slice(connections);
+        self.layers.redraw(widget)
+    }
+    pub fn set_connected(&mut self) -> anyhow::Result<()> {
+        let dashboard = &self.dashboard;
+        for separator in dashboard.connected_box.iter() {
+            self.layers.draw(separator)?;
+        }
+        self.layers.draw(&dashboard.connected)
+    }
+    pub fn update_battery_percentage(&mut self, percentage: &str) -> anyhow::Result<()> {
+        self.dashboard.soc.set_text(&format!("{percentage}%"));
+        self.layers.redraw(&self.dashboard.soc)
+    }
+    pub fn update_sun_data(&mut self, sunrise: &str, sunset: &str) -> anyhow::Result<()> {
+        self.dashboard.sunrise.set_text(sunrise);
+        self.dashboard.sunset.set_text(sunset);
+        self.layers.redraw(&self.dashboard.sunrise)?;
+        self.layers.redraw(&self.dashboard.sunset)
+    }
 
+    pub fn set_forecast_chart(&mut self, hours: Option<usize>) {
+        self.forecast_hours = hours;
+    }
+    pub fn draw_default_weather(&mut self) -> anyhow::Result<()> {
+        let dashboard = &self.dashboard;
+        self.layers.draw(&dashboard.totals_separator)?;
+        for icon in dashboard.sun_icons.iter() {
+            self.layers.draw(icon)?;
+        }
+        match self.forecast_hours {
+            Some(_) => self.layers.redraw(&dashboard.forecast)?,
+            None => {
+                for separator in dashboard.weather_separators.iter() {
+                    self.layers.draw(separator)?;
+                }
+                for icon in dashboard.weather_icons.iter() {
+                    self.layers.draw(icon)?;
+                }
+                self.layers.draw(&dashboard.uv_label)?;
+                for column in dashboard.weather.iter() {
+                    self.layers.redraw(column)?;
+                }
+            }
+        }
+        // sunset and sunrise values
+        self.layers.redraw(&dashboard.sunrise)?;
+        self.layers.redraw(&dashboard.sunset)?;
+        for arrow in dashboard.sun_arrows.iter() {
+            self.layers.draw(arrow)?;
+        }
+        Ok(())
+    }
+    pub fn update_weather_data(
+        &mut self,
+        weather_data: HourlyNew,
+        hour_labels: &[String],
+    ) -> anyhow::Result<()> {
+        if let Some(hours) = self.forecast_hours {
+            let chart = &mut self.dashboard.forecast;
+            let values = |values: &[String]| -> Vec<f32> {
+                values
+                    .iter()
+                    .take(hours)
+                    .map(|value| parse_number(value).unwrap_or(f32::NAN))
+                    .collect()
+            };
+            chart.temperature = values(&weather_data.temperature_2_m);
+            chart.cloud = values(&weather_data.cloud_cover);
+            // open-meteo counts showers separately from the rain
+            chart.rain = values(&weather_data.rain)
+                .into_iter()
+                .zip(
+                    values(&weather_data.showers)
+                        .into_iter()
+                        .chain(std::iter::repeat(0.0)),
+                )
+                .map(|(rain, showers)| rain + showers)
+                .collect();
+            chart.hours = hour_labels.iter().take(hours).cloned().collect();
+            return self.layers.redraw(&self.dashboard.forecast);
+        }
+        for (idx, column) in self.dashboard.weather.iter_mut().enumerate() {
+            let value = |values: &[String]| {
+                values
+                    .get(idx)
+                    .cloned()
+                    .ok_or(anyhow!("error missing data"))
+            };
+            column.rain.set_text(&value(&weather_data.rain)?);
+            column
+                .condition
+                .set_bitmap(condition_icon(condition::classify(&HourValues {
+                    cloud_cover: &value(&weather_data.cloud_cover)?,
+                    rain: &value(&weather_data.rain)?,
+                    showers: &value(&weather_data.showers)?,
+                    temperature: weather_data.temperature_2_m.get(idx).map(String::as_str),
+                    weather_code: weather_data.weather_code.get(idx).map(String::as_str),
+                })));
+            column.uv.set_text(&value(&weather_data.uv_index)?);
+            column.temp.set_text(&value(&weather_data.temperature_2_m)?);
+            column.hour.set_text(&value(hour_labels)?);
+            self.layers.redraw(column)?;
+        }
         Ok(())
     }
     fn draw_default_total(&mut self) -> anyhow::Result<()> {
-        let style = MonoTextStyleBuilder::new()
-            .font(&embedded_graphics::mono_font::ascii::FONT_9X15)
-            .text_color(BinaryColor::On)
-            .build();
-        let style_total = MonoTextStyleBuilder::new()
-            .font(&embedded_graphics::mono_font::ascii::FONT_6X12)
-            .text_color(BinaryColor::On)
-            .build();
-
-        // top right corner display "Total"
-        Text::new("Total", Point::new(154, 80), style_total).draw(self)?;
-        Line::new(Point::new(150, 86), Point::new(190, 86))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(2)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
-        Line::new(Point::new(190, 86), Point::new(190, 70))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .stroke_width(2)
-                    .stroke_color(BinaryColor::On)
-                    .build(),
-            )
-            .draw(self)?;
-        // end
-        //
-        SUN_PATTERN
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 180 + (idx % 18);
-                let y = 90 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-        Text::new("00.00 kW", Point::new(205, 100), style).draw(self)?;
-
-        HOUSE_PATTERN
-            .iter()
-            .enumerate()
-            .map(|(idx, num)| {
-                let x = 180 + (idx % 18);
-                let y = 110 + (idx / 18);
-                let color = {
-                    if num == &0 {
-                        BinaryColor::Off
-                    } else if num == &1 {
-                        BinaryColor::On
-                    } else {
-                        BinaryColor::Off
-                    }
-                };
-                Pixel(Point::new(x as i32, y as i32), color)
-            })
-            .draw(self)?;
-
-        Text::new("00.00 kW", Point::new(205, 120), style).draw(self)?;
+        let dashboard = &self.dashboard;
+        self.layers.draw(&dashboard.totals_title)?;
+        for separator in dashboard.totals_separators.iter() {
+            self.layers.draw(separator)?;
+        }
+        for icon in dashboard.totals_icons.iter() {
+            self.layers.draw(icon)?;
+        }
+        for label in dashboard.totals.iter().chain(dashboard.totals_units.iter()) {
+            self.layers.redraw(label)?;
+        }
         Ok(())
     }
     pub fn update_total_display(
@@ -1292,24 +1433,217 @@ impl DisplayBoxed {
         consumption: &str,
         generated: &str,
     ) -> anyhow::Result<()> {
-        if consumption.len() > 5 || generated.len() > 5 {
-            return Err(anyhow!("input to long"));
+        let totals = &mut self.dashboard.totals;
+        totals[0].set_text(generated);
+        totals[1].set_text(consumption);
+        for label in self.dashboard.totals.iter() {
+            self.layers.redraw(label)?;
         }
-        let style = MonoTextStyleBuilder::new()
-            .font(&embedded_graphics::mono_font::ascii::FONT_9X15)
-            .text_color(BinaryColor::On)
-            .build();
-        self.fill_solid(
-            &Rectangle::new(Point::new(205, 90), Size::new(45, 12)),
-            BinaryColor::Off,
-        )?;
-        self.fill_solid(
-            &Rectangle::new(Point::new(205, 110), Size::new(45, 12)),
-            BinaryColor::Off,
-        )?;
-
-        Text::new(generated, Point::new(205, 100), style).draw(self)?;
-        Text::new(consumption, Point::new(205, 120), style).draw(self)?;
         Ok(())
     }
+    // pv production and house consumption in kW, oldest first, drawn over the totals
+    pub fn draw_chart(&mut self, title: &str, pv: &[f32], house: &[f32]) -> anyhow::Result<()> {
+        let chart = &mut self.dashboard.chart;
+        chart.set_title(title);
+        chart.set_values(0, pv.iter().copied());
+        chart.set_values(1, house.iter().copied());
+        self.layers.redraw(&self.dashboard.chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the design and every supported panel
+    const PANEL_SIZES: [Size; 5] = [
+        Size::new(296, 128),
+        Size::new(250, 122),
+        Size::new(212, 104),
+        Size::new(400, 300),
+        Size::new(800, 480),
+    ];
+
+    // every label of the dashboard with a typical text
+    fn labels(dashboard: &mut Dashboard) -> Vec<(&mut Label, &'static str)> {
+        let mut labels = vec![
+            (&mut dashboard.soc, "100%"),
+            (&mut dashboard.house, "12.34"),
+            (&mut dashboard.battery, "-3.45"),
+            (&mut dashboard.pv, "9.87"),
+            (&mut dashboard.grid, "-0.52"),
+            (&mut dashboard.clock, "12:05PM"),
+            (&mut dashboard.banner.title, "restored"),
+            (&mut dashboard.banner.time, "12:05PM"),
+            (&mut dashboard.uv_label, "UV"),
+            (&mut dashboard.sunrise, "6:12AM"),
+            (&mut dashboard.sunset, "9:45PM"),
+            (&mut dashboard.totals_title, "Total"),
+            (&mut dashboard.earnings, "+9.99"),
+            (&mut dashboard.cost, "-1234"),
+            (&mut dashboard.savings, "gespart 12.34 EUR"),
+            (&mut dashboard.status, "OWN USE"),
+        ];
+        labels.push((&mut dashboard.connected, "Connected"));
+        labels.push((&mut dashboard.totals_warning, "!"));
+        let [co2_today, co2_total] = &mut dashboard.co2;
+        labels.push((co2_today, "CO2 850g"));
+        labels.push((co2_total, "gesamt 815kg"));
+        labels.extend(dashboard.units.iter_mut().map(|label| (label, "kW")));
+        labels.extend(dashboard.totals.iter_mut().map(|label| (label, "99.99")));
+        labels.extend(dashboard.totals_units.iter_mut().map(|label| (label, "kW")));
+        labels.extend(
+            dashboard
+                .estimate
+                .iter_mut()
+                .map(|label| (label, "full 14:35")),
+        );
+        labels.extend(
+            dashboard
+                .metrics
+                .header
+                .iter_mut()
+                .map(|label| (label, "now")),
+        );
+        labels.extend(
+            dashboard
+                .metrics
+                .rows
+                .iter_mut()
+                .flatten()
+                .map(|label| (label, "100")),
+        );
+        for column in dashboard.weather.iter_mut() {
+            labels.push((&mut column.rain, "12.5"));
+            labels.push((&mut column.uv, "10.2"));
+            labels.push((&mut column.temp, "-12.5"));
+            labels.push((&mut column.hour, "12:00"));
+        }
+        labels
+    }
+
+    fn fits(label: &Label) -> bool {
+        let (font, text) = label.fitted();
+        widget::text_width(&text, font) <= label.bounds().size.width
+    }
+
+    #[test]
+    fn dashboard_labels_fit_on_every_panel() {
+        for size in PANEL_SIZES {
+            let mut dashboard = Dashboard::new(Layout::new(size));
+            for (label, typical) in labels(&mut dashboard) {
+                label.set_text("");
+                assert_eq!(label.fitted().1, "");
+                label.set_text(typical);
+                if size == widget::DESIGN_SIZE {
+                    assert_eq!(label.fitted().1, typical, "{} is cut short", typical);
+                }
+                assert!(fits(label), "{} doesn't fit on {:?}", typical, size);
+                label.set_text("an overlong text that never fits anywhere");
+                assert!(
+                    label.fitted().1.ends_with(widget::ELLIPSIS) || label.fitted().1.is_empty()
+                );
+                assert!(fits(label), "overlong text doesn't fit on {:?}", size);
+            }
+        }
+    }
+
+    #[test]
+    fn labels_stay_on_the_panel_and_off_the_circles() {
+        for size in PANEL_SIZES {
+            let mut dashboard = Dashboard::new(Layout::new(size));
+            let panel = Rectangle::new(Point::zero(), size);
+            for (label, typical) in labels(&mut dashboard) {
+                let bounds = label.bounds();
+                assert_eq!(
+                    panel.intersection(&bounds),
+                    bounds,
+                    "{} on {:?}",
+                    typical,
+                    size
+                );
+            }
+            // the values are drawn inside the circles, these around them
+            let [co2_today, co2_total] = &dashboard.co2;
+            let [first, second] = &dashboard.estimate;
+            for label in [
+                &dashboard.soc,
+                &dashboard.status,
+                co2_today,
+                co2_total,
+                first,
+                second,
+            ] {
+                for circle in dashboard.circles.iter() {
+                    assert!(
+                        circle
+                            .bounds()
+                            .intersection(&label.bounds())
+                            .is_zero_sized(),
+                        "{:?} covers a circle on {:?}",
+                        label.bounds(),
+                        size
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_state_label_fits_on_every_panel() {
+        use crate::state::{Language, SystemState};
+
+        // every state is reached by some code, unknown ones included
+        let states: Vec<SystemState> = (0..=u8::MAX).map(SystemState::from_code).collect();
+        for size in PANEL_SIZES {
+            let mut dashboard = Dashboard::new(Layout::new(size));
+            for language in [Language::En, Language::De] {
+                for state in states.iter() {
+                    let label = state.label(language);
+                    dashboard.status.set_text(label);
+                    assert_eq!(dashboard.status.fitted().1, label, "on {:?}", size);
+                    assert!(fits(&dashboard.status), "{} on {:?}", label, size);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn full_screen_pages_draw_into_the_buffers() {
+        let mut display = DisplayBoxed::new();
+        display
+            .display_warning_screen("ERROR", "BACKUP POWER MODE - BATTERY EMPTY", true)
+            .unwrap();
+        display.clear_buffer(prelude::Color::White);
+        display
+            .display_records_page(
+                "Records",
+                ("today", "all time"),
+                &[("PV max", "9.87kW 12:05PM", "10.20kW 2024-06-21"); 6],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn charging_tags_stay_inside_their_bounds() {
+        use embedded_graphics::draw_target::DrawTargetExt;
+        use embedded_graphics::transform::Transform;
+
+        let mut dashboard = Dashboard::new(Layout::default());
+        let charging = &mut dashboard.charging;
+        charging.labels = vec![
+            "BOOST".to_string(),
+            "an overlong charging state".to_string(),
+        ];
+        // the mock display is only 64 pixels wide
+        let offset = Point::new(-90, -80);
+        let mut target = embedded_graphics::mock_display::MockDisplay::new();
+        // the inverted labels fill their background before the text
+        target.set_allow_overdraw(true);
+        charging.draw(&mut target.translated(offset)).unwrap();
+        let drawn = target.affected_area();
+        let bounds = charging.bounds().translate(offset);
+        assert!(!drawn.is_zero_sized());
+        assert_eq!(drawn, drawn.intersection(&bounds));
+    }
 }
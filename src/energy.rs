@@ -0,0 +1,256 @@
+use chrono::NaiveDate;
+use std::time::{Duration, Instant};
+
+use crate::types::PowerValues;
+
+// longer gaps between samples are not integrated, the power in between is unknown
+const MAX_SAMPLE_GAP: Duration = Duration::from_secs(10 * 60);
+// small totals in the morning would otherwise trigger the warning on rounding alone
+const MIN_DEVIATION_KWH: f32 = 0.5;
+
+// energy of the current day in kWh
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyTotals {
+    pub consumption: f32,
+    pub generation: f32,
+    pub grid_import: f32,
+    pub grid_export: f32,
+    pub battery_charge: f32,
+    pub battery_discharge: f32,
+}
+
+impl EnergyTotals {
+    pub fn add(&mut self, other: &EnergyTotals) {
+        self.consumption += other.consumption;
+        self.generation += other.generation;
+        self.grid_import += other.grid_import;
+        self.grid_export += other.grid_export;
+        self.battery_charge += other.battery_charge;
+        self.battery_discharge += other.battery_discharge;
+    }
+    // consumption that did not come from the grid
+    pub fn self_supplied(&self) -> f32 {
+        (self.consumption - self.grid_import).max(0.0)
+    }
+}
+
+// the energy of one local day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayEnergy {
+    pub date: NaiveDate,
+    pub energy: EnergyTotals,
+    // false when the counting started during the day
+    pub complete: bool,
+}
+
+// integrates the power values of the messages, so the totals don't depend on the server
+#[derive(Default)]
+pub struct EnergyCounter {
+    pub today: EnergyTotals,
+    last: Option<(Instant, PowerValues)>,
+    date: Option<NaiveDate>,
+    // after a reboot the morning is missing until the next midnight
+    complete: bool,
+}
+
+impl EnergyCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // returns the energy since the previous message, so prices and factors of that moment
+    // can be applied to it
+    pub fn add(&mut self, power: &PowerValues, now: Instant) -> EnergyTotals {
+        let mut delta = EnergyTotals::default();
+        if let Some((last_time, last)) = self.last {
+            let elapsed = now.duration_since(last_time);
+            if elapsed <= MAX_SAMPLE_GAP {
+                let hours = elapsed.as_secs_f32() / 3600.0;
+                delta.consumption = split_trapezoid(last.house, power.house, hours).0;
+                delta.generation = split_trapezoid(last.pv, power.pv, hours).0;
+                (delta.grid_import, delta.grid_export) =
+                    split_trapezoid(last.grid, power.grid, hours);
+                (delta.battery_charge, delta.battery_discharge) =
+                    split_trapezoid(last.battery, power.battery, hours);
+                self.today.add(&delta);
+            }
+        }
+        self.last = Some((now, *power));
+        delta
+    }
+    // called with the local date on every message, returns the day that ended
+    pub fn roll_over(&mut self, today: NaiveDate) -> Option<DayEnergy> {
+        match self.date {
+            Some(date) if date != today => {
+                let finished = self.day();
+                self.today = EnergyTotals::default();
+                self.date = Some(today);
+                self.complete = true;
+                finished
+            }
+            Some(_) => None,
+            None => {
+                self.date = Some(today);
+                None
+            }
+        }
+    }
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+    // the running day, None until the date is known
+    pub fn day(&self) -> Option<DayEnergy> {
+        self.date.map(|date| DayEnergy {
+            date,
+            energy: self.today,
+            complete: self.complete,
+        })
+    }
+}
+
+// energy above and below zero of a linear change from start to end over the given hours,
+// split at the zero crossing so import and export don't cancel each other out
+pub fn split_trapezoid(start: f32, end: f32, hours: f32) -> (f32, f32) {
+    if start >= 0.0 && end >= 0.0 {
+        return ((start + end) / 2.0 * hours, 0.0);
+    }
+    if start <= 0.0 && end <= 0.0 {
+        return (0.0, -(start + end) / 2.0 * hours);
+    }
+    let zero_at = start.abs() / (start.abs() + end.abs()) * hours;
+    let first = start / 2.0 * zero_at;
+    let second = end / 2.0 * (hours - zero_at);
+    (
+        first.max(0.0) + second.max(0.0),
+        -(first.min(0.0) + second.min(0.0)),
+    )
+}
+
+// threshold is the allowed relative difference, 0.1 for 10%
+pub fn deviates(local: f32, server: f32, threshold: f32) -> bool {
+    (local - server).abs() > MIN_DEVIATION_KWH.max(server.abs() * threshold)
+}
+
+// at most 5 characters so it fits the totals area, the precision is picked after rounding
+// so 99.996 doesn't become "100.00"
+pub fn format_kwh(kwh: f32) -> String {
+    let kwh = kwh.min(99999.0);
+    (0..=2)
+        .rev()
+        .map(|precision| format!("{:.*}", precision, kwh))
+        .find(|text| text.len() <= 5)
+        .unwrap_or_else(|| format!("{:.0}", kwh))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(house: f32, pv: f32, battery: f32, grid: f32) -> PowerValues {
+        PowerValues {
+            house,
+            pv,
+            battery,
+            grid,
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    #[test]
+    fn trapezoid_is_split_at_the_zero_crossing() {
+        assert_eq!(split_trapezoid(1.0, 3.0, 0.5), (1.0, 0.0));
+        assert_eq!(split_trapezoid(-1.0, -3.0, 0.5), (0.0, 1.0));
+        // from 1 kW import to 1 kW export in one hour, half an hour each way
+        assert_eq!(split_trapezoid(1.0, -1.0, 1.0), (0.25, 0.25));
+        // the crossing is a quarter of the way in
+        let (import, export) = split_trapezoid(-1.0, 3.0, 1.0);
+        assert!((import - 1.125).abs() < 1e-6, "{}", import);
+        assert!((export - 0.125).abs() < 1e-6, "{}", export);
+    }
+
+    #[test]
+    fn power_is_integrated_between_messages() {
+        let start = Instant::now();
+        let mut counter = EnergyCounter::new();
+        assert_eq!(
+            counter.add(&power(2.0, 4.0, 1.0, -1.0), start),
+            EnergyTotals::default()
+        );
+        let delta = counter.add(
+            &power(2.0, 4.0, -1.0, 1.0),
+            start + Duration::from_secs(6 * 60),
+        );
+        assert!((delta.consumption - 0.2).abs() < 1e-6);
+        assert!((delta.generation - 0.4).abs() < 1e-6);
+        // import and export, charge and discharge half of the time each
+        assert!((delta.grid_import - 0.025).abs() < 1e-6);
+        assert!((delta.grid_export - 0.025).abs() < 1e-6);
+        assert!((delta.battery_charge - 0.025).abs() < 1e-6);
+        assert!((delta.battery_discharge - 0.025).abs() < 1e-6);
+        assert_eq!(counter.today, delta);
+    }
+
+    #[test]
+    fn long_gaps_are_skipped() {
+        let start = Instant::now();
+        let mut counter = EnergyCounter::new();
+        counter.add(&power(2.0, 0.0, 0.0, 2.0), start);
+        let after_gap = start + MAX_SAMPLE_GAP + Duration::from_secs(1);
+        assert_eq!(
+            counter.add(&power(2.0, 0.0, 0.0, 2.0), after_gap),
+            EnergyTotals::default()
+        );
+        // the next interval counts again
+        let delta = counter.add(
+            &power(2.0, 0.0, 0.0, 2.0),
+            after_gap + Duration::from_secs(6 * 60),
+        );
+        assert!((delta.consumption - 0.2).abs() < 1e-6);
+        assert_eq!(counter.today, delta);
+    }
+
+    #[test]
+    fn days_roll_over_at_midnight() {
+        let start = Instant::now();
+        let mut counter = EnergyCounter::new();
+        assert_eq!(counter.day(), None);
+        assert_eq!(counter.roll_over(date(1)), None);
+        counter.add(&power(1.0, 0.0, 0.0, 1.0), start);
+        counter.add(&power(1.0, 0.0, 0.0, 1.0), start + Duration::from_secs(360));
+        assert_eq!(counter.roll_over(date(1)), None);
+        // the first day started with the device, it is incomplete
+        let first = counter.roll_over(date(2)).unwrap();
+        assert_eq!(first.date, date(1));
+        assert!(!first.complete);
+        assert!((first.energy.consumption - 0.1).abs() < 1e-6);
+        assert_eq!(counter.today, EnergyTotals::default());
+        assert!(counter.is_complete());
+        let second = counter.roll_over(date(3)).unwrap();
+        assert_eq!((second.date, second.complete), (date(2), true));
+        assert_eq!(counter.day().unwrap().date, date(3));
+    }
+
+    #[test]
+    fn small_deviations_are_ignored() {
+        // below the 0.5 kWh floor
+        assert!(!deviates(0.4, 0.0, 0.1));
+        assert!(!deviates(1.4, 1.0, 0.1));
+        assert!(deviates(1.6, 1.0, 0.1));
+        // 10% of 20 kWh is more than the floor
+        assert!(!deviates(21.9, 20.0, 0.1));
+        assert!(deviates(22.1, 20.0, 0.1));
+        assert!(deviates(17.9, 20.0, 0.1));
+    }
+
+    #[test]
+    fn kwh_fit_in_five_characters() {
+        assert_eq!(format_kwh(9.99), "9.99");
+        assert_eq!(format_kwh(9.999), "10.00");
+        assert_eq!(format_kwh(99.9), "99.90");
+        assert_eq!(format_kwh(99.996), "100.0");
+        assert_eq!(format_kwh(1000.0), "1000");
+        assert_eq!(format_kwh(123456.0), "99999");
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::time::{Duration, Instant, SystemTime};
+
+// the clock starts at 1970 until it is set, so anything before late 2023 can't be trusted
+const MIN_VALID_UNIX_TIME: i64 = 1_700_000_000;
+
+// accepts unix seconds, rfc3339 and iso timestamps without an offset, which are local times in tz
+pub fn parse_timestamp(ts: &str, tz: Tz) -> Option<DateTime<Utc>> {
+    let ts = ts.trim();
+    if let Ok(secs) = ts.parse::<i64>() {
+        return DateTime::from_timestamp(secs, 0);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(ts) {
+        return Some(time.with_timezone(&Utc));
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(ts, format).ok())
+        .and_then(|time| {
+            // the earlier of a time repeated when dst ends, a time skipped when it starts is moved
+            // past the gap
+            tz.from_local_datetime(&time).earliest().or_else(|| {
+                tz.from_local_datetime(&(time + TimeDelta::hours(1)))
+                    .earliest()
+            })
+        })
+        .map(|time| time.with_timezone(&Utc))
+}
+
+pub fn device_time_valid(now: SystemTime) -> bool {
+    let now: DateTime<Utc> = now.into();
+    now.timestamp() >= MIN_VALID_UNIX_TIME
+}
+
+pub struct FreshnessTracker {
+    stale_after: Duration,
+    // of the timestamps without an offset
+    tz: Tz,
+    last_ts: Option<DateTime<Utc>>,
+    last_received: Option<Instant>,
+    stale: bool,
+}
+
+impl FreshnessTracker {
+    pub fn new(stale_after: Duration, tz: Tz) -> Self {
+        FreshnessTracker {
+            stale_after,
+            tz,
+            last_ts: None,
+            last_received: None,
+            stale: false,
+        }
+    }
+    pub fn update(&mut self, ts: &str) {
+        self.last_ts = parse_timestamp(ts, self.tz);
+        self.last_received = Some(Instant::now());
+    }
+    // compares the ts of the last message with the device clock, without a usable
+    // timestamp or clock the time since the message was received is used instead
+    pub fn age(&self, now: SystemTime) -> Option<Duration> {
+        let received = self.last_received?;
+        match self.last_ts {
+            Some(ts) if device_time_valid(now) => {
+                let now: DateTime<Utc> = now.into();
+                // a server clock running ahead of ours counts as fresh
+                Some((now - ts).to_std().unwrap_or(Duration::ZERO))
+            }
+            _ => Some(received.elapsed()),
+        }
+    }
+    // returns the new state when the data went stale or became fresh again
+    pub fn check(&mut self, now: SystemTime) -> Option<bool> {
+        let stale = self.age(now).is_some_and(|age| age > self.stale_after);
+        if stale == self.stale {
+            return None;
+        }
+        self.stale = stale;
+        Some(stale)
+    }
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(ts: &str) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(ts)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+
+    #[test]
+    fn timestamps_with_an_offset_ignore_the_zone() {
+        let berlin = chrono_tz::Europe::Berlin;
+        assert_eq!(
+            parse_timestamp("1718971200", berlin),
+            utc("2024-06-21T12:00:00Z")
+        );
+        assert_eq!(
+            parse_timestamp("2024-06-21T14:00:00+02:00", berlin),
+            utc("2024-06-21T12:00:00Z")
+        );
+        assert_eq!(parse_timestamp("2:05PM", berlin), None);
+    }
+
+    #[test]
+    fn naive_timestamps_are_local_times() {
+        let berlin = chrono_tz::Europe::Berlin;
+        assert_eq!(
+            parse_timestamp("2024-06-21T14:00", berlin),
+            utc("2024-06-21T12:00:00Z")
+        );
+        assert_eq!(
+            parse_timestamp("2024-01-10 14:00:00", berlin),
+            utc("2024-01-10T13:00:00Z")
+        );
+        assert_eq!(
+            parse_timestamp("2024-06-21T14:00:00", Tz::UTC),
+            utc("2024-06-21T14:00:00Z")
+        );
+    }
+
+    #[test]
+    fn naive_timestamps_around_dst_changes() {
+        let berlin = chrono_tz::Europe::Berlin;
+        // 2:30 happens twice on the last sunday of october
+        assert_eq!(
+            parse_timestamp("2024-10-27T02:30", berlin),
+            utc("2024-10-27T00:30:00Z")
+        );
+        // and not at all on the last sunday of march
+        assert_eq!(
+            parse_timestamp("2024-03-31T02:30", berlin),
+            utc("2024-03-31T01:30:00Z")
+        );
+    }
+}
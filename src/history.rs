@@ -0,0 +1,245 @@
+use heapless::HistoryBuffer;
+
+use crate::types::PowerValues;
+
+// 24 hours at the default resolution of 5 minutes
+pub const HISTORY_LEN: usize = 288;
+
+// one averaged sample per resolution interval, powers in kW and soc in percent
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sample {
+    // unix seconds at the start of the interval
+    pub timestamp: u32,
+    pub house: f32,
+    pub pv: f32,
+    pub battery: f32,
+    pub grid: f32,
+    pub soc: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    House,
+    Pv,
+    Battery,
+    Grid,
+    Soc,
+}
+
+impl Sample {
+    pub fn get(&self, field: Field) -> f32 {
+        match field {
+            Field::House => self.house,
+            Field::Pv => self.pv,
+            Field::Battery => self.battery,
+            Field::Grid => self.grid,
+            Field::Soc => self.soc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub min: f32,
+    pub max: f32,
+    pub average: f32,
+}
+
+// the samples of the interval that is not finished yet
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: u32,
+    sums: [f32; 5],
+    count: u32,
+}
+
+// keeps the last N intervals, older ones are overwritten
+pub struct History<const N: usize> {
+    samples: HistoryBuffer<Sample, N>,
+    resolution: u32,
+    bucket: Option<Bucket>,
+}
+
+impl<const N: usize> History<N> {
+    pub fn new(resolution_secs: u32) -> Self {
+        History {
+            samples: HistoryBuffer::new(),
+            resolution: resolution_secs.max(1),
+            bucket: None,
+        }
+    }
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+    pub fn push(&mut self, timestamp: u32, power: &PowerValues, soc: f32) {
+        let start = timestamp - timestamp % self.resolution;
+        match self.bucket {
+            Some(bucket) if bucket.start == start => (),
+            // a clock going backwards (sntp) also closes the interval
+            Some(_) => {
+                self.flush();
+                self.bucket = Some(Bucket::new(start));
+            }
+            None => self.bucket = Some(Bucket::new(start)),
+        }
+        if let Some(bucket) = self.bucket.as_mut() {
+            let values = [power.house, power.pv, power.battery, power.grid, soc];
+            for (sum, value) in bucket.sums.iter_mut().zip(values) {
+                *sum += value;
+            }
+            bucket.count += 1;
+        }
+    }
+    // stores the unfinished interval
+    pub fn flush(&mut self) {
+        if let Some(bucket) = self.bucket.take() {
+            if bucket.count > 0 {
+                self.samples.write(bucket.average());
+            }
+        }
+    }
+    // restored samples from flash go in without averaging
+    pub fn insert(&mut self, sample: Sample) {
+        self.samples.write(sample);
+    }
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+    // oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.oldest_ordered()
+    }
+    pub fn since(&self, from: u32) -> impl Iterator<Item = &Sample> {
+        self.iter().filter(move |sample| sample.timestamp >= from)
+    }
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.recent()
+    }
+    pub fn stats(&self, field: Field, from: u32) -> Option<Stats> {
+        let mut count = 0;
+        let mut stats = Stats {
+            min: f32::MAX,
+            max: f32::MIN,
+            average: 0.0,
+        };
+        for sample in self.since(from) {
+            let value = sample.get(field);
+            stats.min = stats.min.min(value);
+            stats.max = stats.max.max(value);
+            stats.average += value;
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        stats.average /= count as f32;
+        Some(stats)
+    }
+    pub fn min(&self, field: Field, from: u32) -> Option<f32> {
+        self.stats(field, from).map(|stats| stats.min)
+    }
+    pub fn max(&self, field: Field, from: u32) -> Option<f32> {
+        self.stats(field, from).map(|stats| stats.max)
+    }
+    pub fn average(&self, field: Field, from: u32) -> Option<f32> {
+        self.stats(field, from).map(|stats| stats.average)
+    }
+}
+
+impl Bucket {
+    fn new(start: u32) -> Self {
+        Bucket {
+            start,
+            sums: [0.0; 5],
+            count: 0,
+        }
+    }
+    fn average(&self) -> Sample {
+        let count = self.count as f32;
+        Sample {
+            timestamp: self.start,
+            house: self.sums[0] / count,
+            pv: self.sums[1] / count,
+            battery: self.sums[2] / count,
+            grid: self.sums[3] / count,
+            soc: self.sums[4] / count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(house: f32) -> PowerValues {
+        PowerValues {
+            house,
+            pv: 2.0 * house,
+            ..PowerValues::default()
+        }
+    }
+
+    #[test]
+    fn samples_are_averaged_per_interval() {
+        let mut history = History::<8>::new(300);
+        history.push(600, &power(1.0), 50.0);
+        history.push(899, &power(3.0), 60.0);
+        assert!(history.is_empty());
+        // the next interval closes the first one
+        history.push(900, &power(5.0), 70.0);
+        assert_eq!(history.len(), 1);
+        let first = history.latest().unwrap();
+        assert_eq!(first.timestamp, 600);
+        assert_eq!((first.house, first.pv, first.soc), (2.0, 4.0, 55.0));
+        history.flush();
+        assert_eq!(history.latest().unwrap().timestamp, 900);
+        assert_eq!(history.latest().unwrap().house, 5.0);
+    }
+
+    #[test]
+    fn a_clock_going_backwards_closes_the_interval() {
+        let mut history = History::<8>::new(300);
+        history.push(1000, &power(1.0), 50.0);
+        history.push(500, &power(3.0), 50.0);
+        history.flush();
+        let timestamps: Vec<u32> = history.iter().map(|sample| sample.timestamp).collect();
+        assert_eq!(timestamps, [900, 300]);
+    }
+
+    #[test]
+    fn old_samples_are_overwritten() {
+        let mut history = History::<3>::new(60);
+        for minute in 0..5 {
+            history.push(minute * 60, &power(minute as f32), 50.0);
+        }
+        history.flush();
+        assert_eq!(history.len(), 3);
+        let houses: Vec<f32> = history.iter().map(|sample| sample.house).collect();
+        assert_eq!(houses, [2.0, 3.0, 4.0]);
+        assert_eq!(history.since(180).count(), 2);
+    }
+
+    #[test]
+    fn stats_cover_the_samples_since() {
+        let mut history = History::<8>::new(60);
+        for (minute, house) in [1.0, 4.0, 2.0, 5.0].into_iter().enumerate() {
+            history.push(minute as u32 * 60, &power(house), 50.0);
+        }
+        history.flush();
+        assert_eq!(
+            history.stats(Field::House, 0),
+            Some(Stats {
+                min: 1.0,
+                max: 5.0,
+                average: 3.0,
+            })
+        );
+        assert_eq!(history.min(Field::House, 60), Some(2.0));
+        assert_eq!(history.max(Field::Pv, 0), Some(10.0));
+        assert_eq!(history.average(Field::Soc, 0), Some(50.0));
+        assert_eq!(history.stats(Field::House, 1000), None);
+    }
+}
@@ -0,0 +1,4 @@
+// the icons are converted from the files in assets/ by build.rs, one constant per file
+use crate::widget::Bitmap;
+
+include!(concat!(env!("OUT_DIR"), "/icons.rs"));
@@ -0,0 +1,25 @@
+// everything but the wifi and websocket handling, so it can be tested on the host. The parts that
+// need the esp32, like the flash storage, are left out there
+pub mod battery;
+pub mod clock;
+pub mod co2;
+pub mod condition;
+pub mod config;
+pub mod dirty;
+pub mod display;
+pub mod energy;
+pub mod freshness;
+pub mod history;
+pub mod icons;
+pub mod metrics;
+pub mod pages;
+pub mod panel;
+pub mod peaks;
+pub mod refresh;
+pub mod rollup;
+pub mod sequence;
+pub mod state;
+pub mod storage;
+pub mod tariff;
+pub mod types;
+pub mod widget;
@@ -1,9 +1,9 @@
 pub mod client;
-pub mod display;
-pub mod types;
 pub mod wifi;
 
-use client::{convert_connect_error, create_tcp_conn_and_client};
+use client::{
+    convert_connect_error, create_tcp_conn_and_client, is_read_timeout, request_full_state,
+};
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::mono_font::MonoTextStyleBuilder;
 use embedded_graphics::pixelcolor::BinaryColor;
@@ -11,16 +11,34 @@ use embedded_graphics::prelude::Point;
 use embedded_graphics::text::{Text, TextStyleBuilder};
 use embedded_graphics::Drawable;
 use embedded_websocket::framer::{Framer, ReadResult};
-use epd_waveshare::prelude::WaveshareDisplay;
 use epd_waveshare::prelude::*;
-use esp_idf_hal::delay;
-use std::time::Duration;
+use std::time::{Instant, SystemTime};
 
 use anyhow::{anyhow, Result};
 use esp_idf_hal::peripherals::Peripherals;
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
 
-use crate::display::init_display;
 use crate::wifi::connect_to_wifi;
+use senec_c::battery::{BatteryEstimator, Estimate};
+use senec_c::clock::{Clock, TimeFormat};
+use senec_c::co2::Co2Counter;
+use senec_c::config::Config;
+use senec_c::display::{init_display, DisplayBoxed};
+use senec_c::energy::{DayEnergy, EnergyCounter};
+use senec_c::freshness::FreshnessTracker;
+use senec_c::history::{History, HISTORY_LEN};
+use senec_c::metrics::Metrics;
+use senec_c::pages::{Page, PageRotation};
+use senec_c::panel::Panel;
+use senec_c::peaks::Peaks;
+use senec_c::refresh::RefreshScheduler;
+use senec_c::rollup::Rollups;
+use senec_c::sequence::{SequenceEvent, SequenceTracker};
+use senec_c::state::{ChargingInfo, Language, Severity, SystemState};
+use senec_c::storage::Storage;
+use senec_c::tariff::Costs;
+use senec_c::types::{parse_number, PowerValues};
+use senec_c::{battery, co2, display, energy, metrics, panel, peaks, rollup, state, tariff, types};
 
 fn main() -> Result<()> {
     let wifi_password = option_env!("WIFI_PASS").ok_or(anyhow!("wifi_pass not set"))?;
@@ -29,15 +47,14 @@ fn main() -> Result<()> {
     esp_idf_svc::sys::link_patches();
 
     esp_idf_svc::log::EspLogger::initialize_default();
+    let config = Config::from_env();
+    let time_format = config.time_format();
 
     // get peripherals
     let peripherals = Peripherals::take()?;
 
-    // connecting to wifi
-    let mut _wifi = connect_to_wifi(peripherals.modem, wifi_ssid, wifi_password)?;
-
     // setting up display
-    let (mut display, mut epd, mut driver) = init_display(
+    let (mut display, mut panel) = init_display(
         peripherals.spi2,
         peripherals.pins.gpio48,
         peripherals.pins.gpio38,
@@ -47,6 +64,7 @@ fn main() -> Result<()> {
         peripherals.pins.gpio17,
     )?;
     log::info!("Got the display");
+    display.set_forecast_chart(config.forecast_chart);
     let default_text_style = MonoTextStyleBuilder::new()
         .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
         .text_color(BinaryColor::On)
@@ -55,23 +73,86 @@ fn main() -> Result<()> {
         .baseline(embedded_graphics::text::Baseline::Top)
         .build();
 
+    // on the heap, it is a few kB
+    let mut history: Box<History<HISTORY_LEN>> = Box::new(History::new(config.history_resolution));
+    let nvs = EspDefaultNvsPartition::take()?;
+    let mut storage = Storage::new(nvs.clone(), config.persist_interval)?;
+    storage.load_history(&mut history);
+    let mut co2 = Co2Counter::new(config.co2_factor, storage.load_co2_total());
+    let mut rollups = Rollups::new();
+    storage.load_rollups(&mut rollups);
+    let mut peaks = storage.load_peaks();
+    let mut last_message = storage.load_last_message();
+    // shown until the first message arrives
+    let mut restored = last_message.is_some();
+    if let Some(message) = &last_message {
+        log::info!("Showing the values from before the reboot");
+        panel.clear_frame()?;
+        display.clear(BinaryColor::Off)?;
+        display.draw_default_display()?;
+        if let Err(e) = draw_last_known(
+            &mut display,
+            message,
+            &time_format,
+            config.language,
+            restored,
+            config.low_battery,
+        ) {
+            log::info!("Error drawing the restored values: {:?}", e);
+        }
+        panel.full_refresh(display.frame())?;
+    }
+
+    // connecting to wifi
+    let mut _wifi = connect_to_wifi(peripherals.modem, nvs, wifi_ssid, wifi_password)?;
+    let mut clock = Clock::start(&config.ntp_servers)?;
+
+    let mut sequence_tracker = SequenceTracker::new();
+    let mut freshness = FreshnessTracker::new(config.stale_after, config.timezone);
+    let mut update_time = String::new();
+    let mut shown_clock = String::new();
+    let mut last_state = None;
+    // the state the warning screen is shown for, the dashboard is hidden while it is set
+    let mut warning: Option<SystemState> = None;
+    let mut energy = EnergyCounter::new();
+    let mut costs = Costs::default();
+    let mut totals_deviation = false;
+    let mut battery_estimator = config.battery_capacity.map(BatteryEstimator::new);
+    let mut pages = PageRotation::new(&[
+        (Page::Dashboard, config.dashboard_page),
+        (Page::Chart, config.chart_page),
+        (Page::Records, config.records_page),
+        (Page::Periods, config.periods_page),
+    ]);
     let mut retries = 0;
     loop {
         // Clear the display from any remainders
-        epd.clear_frame(&mut driver, &mut delay::Ets)?;
+        panel.clear_frame()?;
         display.clear(BinaryColor::Off)?;
 
         // draw the ui with default values
-        display.draw_default_display(default_text_style)?;
-        epd.update_and_display_frame(&mut driver, display.buffer(), &mut delay::Ets)?;
-        epd.update_old_frame(&mut driver, display.buffer(), &mut delay::Ets)?;
+        display.draw_default_display()?;
+        // keep showing what we know while (re)connecting
+        if let Some(message) = &last_message {
+            if let Err(e) = draw_last_known(
+                &mut display,
+                message,
+                &time_format,
+                config.language,
+                restored,
+                config.low_battery,
+            ) {
+                log::info!("Error drawing the last known values: {:?}", e);
+            }
+        }
+        panel.full_refresh(display.frame())?;
 
         if retries > 5 {
             break;
         }
         log::info!("Retry: {}", retries);
         let mut read_cursor = 0;
-        // we dont need this after the intial request was send
+        // used for the handshake and for requesting a full state resend
         let mut write_buf = [0; 500];
         let mut read_buf = [0; 500];
 
@@ -92,13 +173,16 @@ fn main() -> Result<()> {
         };
 
         log::info!("Connected to websocket");
+        sequence_tracker.reset();
         display.set_connected()?;
-        epd.update_new_frame(&mut driver, display.buffer(), &mut delay::Ets)?;
-        epd.display_new_frame(&mut driver, &mut delay::Ets)?;
-        epd.update_old_frame(&mut driver, display.buffer(), &mut delay::Ets)?;
-
-        // start time
-        let mut curr_time = std::time::SystemTime::now();
+        // the screen was fully refreshed above
+        let mut refresh = RefreshScheduler::new(
+            config.refresh,
+            panel::size(),
+            panel::PARTIAL_REFRESH,
+            Instant::now(),
+        );
+        quick_refresh(&mut panel, &mut display, &mut refresh)?;
 
         let mut flushed = true;
         'inner: loop {
@@ -108,134 +192,321 @@ fn main() -> Result<()> {
                     ReadResult::Pong(_) => continue,
                     ReadResult::Closed => continue,
                     ReadResult::Text(t) => {
-                        let time_now = std::time::SystemTime::now();
-                        let since = time_now.duration_since(curr_time)?;
-                        if since > Duration::from_secs(120) {
+                        let time_now = Instant::now();
+                        // pages only switch when a message comes in
+                        let page_changed = pages.poll(time_now).is_some();
+                        if page_changed && pages.page().shows_dashboard() && warning.is_none() {
+                            // the other pages covered all or part of the dashboard
                             display.clear_buffer(Color::White);
-                            display.draw_default_display(default_text_style)?;
+                            display.draw_default_display()?;
                             display.set_connected()?;
-                            epd.update_and_display_frame(
-                                &mut driver,
-                                display.buffer(),
-                                &mut delay::Ets,
-                            )?;
-                            epd.update_old_frame(&mut driver, display.buffer(), &mut delay::Ets)?;
-                            curr_time = time_now;
                             flushed = true;
                         }
+                        let minute = clock.now().map(|now| time_format.minute_of_day(now));
                         log::info!("Got a message {}", t);
 
                         match serde_json::from_str::<types::NewUiStruct>(t) {
                             Ok(json_values) => {
-                                display.clear_text()?;
-                                display.draw_text(
-                                    default_text_style,
-                                    json_values.gui_house_pow,
-                                    &match json_values.gui_bat_data_power.contains("-") {
-                                        // meaning the battery is being charged
-                                        false => {
-                                            format!("+{}", json_values.gui_bat_data_fuel_charge)
-                                        }
-                                        // meaning battery is being discharged
-                                        true => {
-                                            format!("-{}", json_values.gui_bat_data_fuel_charge)
-                                        }
-                                    },
-                                    json_values.gui_inverter_power,
-                                    &match json_values.gui_grid_pow.starts_with("-") {
-                                        true => format!("{}", json_values.gui_grid_pow),
-                                        false => format!("+{}", json_values.gui_grid_pow),
-                                    },
-                                    json_values.ts,
-                                )?;
+                                let event =
+                                    sequence_tracker.track(json_values.seq, json_values.server_id);
+                                match event {
+                                    SequenceEvent::Gap { expected, received } => {
+                                        log::warn!(
+                                            "Missed messages {} to {}",
+                                            expected,
+                                            received - 1
+                                        );
+                                    }
+                                    SequenceEvent::Duplicate(seq) => {
+                                        log::warn!("Skipping duplicate message {}", seq);
+                                        continue;
+                                    }
+                                    SequenceEvent::Restart(seq) => {
+                                        log::warn!("Server restarted, now at message {}", seq);
+                                    }
+                                    _ => (),
+                                }
+                                if event.needs_resend() {
+                                    // the totals and weather are only sent when they change,
+                                    // so they may be out of date now
+                                    if let Err(e) = request_full_state(&mut framer, &mut stream) {
+                                        log::info!("Error requesting full state: {:?}", e);
+                                    }
+                                    flushed = true;
+                                }
 
-                                // to the house always active
-                                display
-                                    .draw_connections(display::ConnectionDirection::Top(true))?;
+                                freshness.update(json_values.ts);
+                                update_time = time_format.timestamp(json_values.ts);
+                                // the device clock is shown once it is synced, the server time until then
+                                shown_clock = match clock.now() {
+                                    Some(now) => time_format.time(now),
+                                    None => update_time.clone(),
+                                };
 
-                                // will rework the conditions in the future
+                                let (system_state, state_code) =
+                                    SystemState::parse(json_values.stat_state);
+                                if last_state != Some(system_state) {
+                                    log::info!(
+                                        "System state {:?} ({})",
+                                        system_state,
+                                        json_values.stat_state
+                                    );
+                                    last_state = Some(system_state);
+                                }
+                                match system_state.severity() {
+                                    Severity::Normal => {
+                                        if warning.take().is_some() {
+                                            // back to normal, bring the dashboard back
+                                            display.clear_buffer(Color::White);
+                                            display.draw_default_display()?;
+                                            display.set_connected()?;
+                                            flushed = true;
+                                        }
+                                    }
+                                    severity => {
+                                        if warning != Some(system_state) {
+                                            let detail = state_code
+                                                .and_then(state::state_name)
+                                                .unwrap_or(json_values.stat_state);
+                                            display.clear_buffer(Color::White);
+                                            display.display_warning_screen(
+                                                system_state.label(config.language),
+                                                detail,
+                                                severity == Severity::Fault,
+                                            )?;
+                                            panel.full_refresh(display.frame())?;
+                                            warning = Some(system_state);
+                                        }
+                                    }
+                                }
 
-                                if json_values.gui_bat_data_power != "0.00"
-                                    && !json_values.gui_bat_data_power.starts_with("-")
-                                {
-                                    // to the battery since it is being discharged
-                                    display.draw_connections(
-                                        display::ConnectionDirection::Bottom(false),
-                                    )?;
+                                // the daily counters start over at local midnight
+                                let now = clock.now();
+                                if let Some(now) = now {
+                                    if let Some(finished) =
+                                        energy.roll_over(time_format.local_date(now))
+                                    {
+                                        log::info!("Energy of the finished day: {:?}", finished);
+                                        log::info!("Costs of the finished day: {:?}", costs);
+                                        costs = Costs::default();
+                                        co2.reset_day();
+                                        rollups.add_day(&finished);
+                                        if let Err(e) = storage.save_rollups(&rollups) {
+                                            log::info!("Error saving the rollups: {:?}", e);
+                                        }
+                                    }
                                 }
-                                if json_values.gui_bat_data_power.starts_with("-")
-                                    && json_values.gui_bat_data_power != "0.00"
-                                {
-                                    display.draw_connections(
-                                        display::ConnectionDirection::Bottom(false),
-                                    )?
-                                } else if !json_values.gui_bat_data_power.starts_with("-")
-                                    && json_values.gui_bat_data_power != "0.00"
+                                let power = PowerValues::parse(&json_values);
+                                let soc = parse_number(json_values.gui_bat_data_fuel_charge);
+                                if let Some(power) = power {
+                                    let delta = energy.add(&power, Instant::now());
+                                    co2.add(&delta);
+                                    // without a synced clock the band is unknown, the base price is used
+                                    if let Some(tariff) = &config.tariff {
+                                        costs.add(
+                                            &delta,
+                                            tariff,
+                                            now.map(|now| time_format.minute_of_day(now)),
+                                        );
+                                    }
+                                    // before the clock is set the samples would be filed in 1970
+                                    if let (Some(now), Some(soc)) = (now, soc) {
+                                        history.push(now.timestamp() as u32, &power, soc);
+                                    }
+                                    if let Some(now) = now {
+                                        peaks.start_day(time_format.day_start(now));
+                                        if peaks.update(&power, soc, now.timestamp() as u32) {
+                                            log::info!("New peaks: {:?}", peaks.today);
+                                        }
+                                    }
+                                }
+                                if let (Some(estimator), Some(power)) =
+                                    (battery_estimator.as_mut(), power)
                                 {
-                                    // to the battery since it is being charged
-                                    display.draw_connections(
-                                        display::ConnectionDirection::Bottom(true),
-                                    )?;
+                                    estimator.update(power.battery, Instant::now());
                                 }
 
-                                // power send to the grid
-                                if json_values.gui_grid_pow.starts_with("-")
-                                    && json_values.gui_grid_pow != "-0.00"
-                                {
-                                    display.draw_connections(
-                                        display::ConnectionDirection::Right(true),
-                                    )?;
-                                } else if !json_values.gui_grid_pow.starts_with("-")
-                                    && json_values.gui_grid_pow != "0.00"
+                                last_message = Some(t.to_string());
+                                restored = false;
+                                if let Err(e) = storage.save_if_due(t, &history, co2.total, &peaks)
                                 {
-                                    // power taken from the grid
-                                    display.draw_connections(
-                                        display::ConnectionDirection::Right(false),
-                                    )?;
+                                    log::info!("Error saving to flash: {:?}", e);
+                                }
+                                // the counters keep running, only the drawing waits for the warning
+                                if warning.is_some() {
+                                    continue;
                                 }
 
-                                if json_values.gui_inverter_power != "0.00"
-                                    && !json_values.gui_inverter_power.starts_with("-")
-                                {
-                                    display.draw_connections(
-                                        display::ConnectionDirection::Left(false),
-                                    )?;
+                                draw_power_values(
+                                    &mut display,
+                                    &json_values,
+                                    &shown_clock,
+                                    config.low_battery,
+                                )?;
+                                freshness.check(SystemTime::now());
+                                if freshness.is_stale() {
+                                    display.mark_stale(&update_time)?;
+                                }
+                                display.draw_status(system_state.label(config.language))?;
+                                let charging_info = ChargingInfo::parse(
+                                    json_values.gui_charging_info,
+                                    json_values.gui_boosting_info,
+                                );
+                                let mut charging_labels = Vec::new();
+                                if charging_info.grid_charging {
+                                    charging_labels
+                                        .push(ChargingInfo::grid_charging_label(config.language));
                                 }
+                                if charging_info.boosting {
+                                    charging_labels
+                                        .push(ChargingInfo::boosting_label(config.language));
+                                }
+                                display.draw_charging_info(&charging_labels)?;
+
+                                if let Some(estimator) = battery_estimator.as_mut() {
+                                    let estimate = soc
+                                        .map(|soc| estimator.estimate(soc))
+                                        .unwrap_or(Estimate::Idle);
+                                    let empty_at = match (estimate, now) {
+                                        (Estimate::EmptyIn(duration), Some(now)) => {
+                                            Some(time_format.unix_time(
+                                                now.timestamp() as u32 + duration.as_secs() as u32,
+                                            ))
+                                        }
+                                        _ => None,
+                                    };
+                                    match battery::lines(estimate, empty_at, config.language) {
+                                        Some((first, second)) => {
+                                            display.draw_battery_estimate(first, &second)?
+                                        }
+                                        None => display.draw_battery_estimate("", "")?,
+                                    }
+                                }
+                                let live =
+                                    power.map(|power| Metrics::live(&power)).unwrap_or_default();
+                                let server_totals = match (
+                                    parse_number(json_values.total_data.consumption),
+                                    parse_number(json_values.total_data.generated),
+                                ) {
+                                    (Some(consumption), Some(generated)) => {
+                                        Some((consumption, generated))
+                                    }
+                                    _ => None,
+                                };
+                                // the local counters fill in when the server doesn't send totals
+                                let (consumption, generated) = server_totals
+                                    .unwrap_or((energy.today.consumption, energy.today.generation));
+                                let daily = Metrics::daily(consumption, generated, &energy.today);
+                                // only compared once the local counters ran for a whole day
+                                let deviation = match server_totals {
+                                    Some((consumption, generated)) if energy.is_complete() => {
+                                        energy::deviates(
+                                            energy.today.consumption,
+                                            consumption,
+                                            config.energy_deviation,
+                                        ) || energy::deviates(
+                                            energy.today.generation,
+                                            generated,
+                                            config.energy_deviation,
+                                        )
+                                    }
+                                    _ => false,
+                                };
+                                if deviation && !totals_deviation {
+                                    log::warn!(
+                                        "Server totals {:?} differ from the local ones {:?}",
+                                        server_totals,
+                                        energy.today
+                                    );
+                                }
+                                totals_deviation = deviation;
+                                display.draw_totals_warning(deviation)?;
+                                let (ss_label, sc_label) = metrics::labels(config.language);
+                                display.draw_metrics([
+                                    (
+                                        ss_label,
+                                        &metrics::format_percent(live.self_sufficiency),
+                                        &metrics::format_percent(daily.self_sufficiency),
+                                    ),
+                                    (
+                                        sc_label,
+                                        &metrics::format_percent(live.self_consumption),
+                                        &metrics::format_percent(daily.self_consumption),
+                                    ),
+                                ])?;
 
                                 if json_values.total_data.new || flushed {
-                                    display.update_total_display(
-                                        json_values.total_data.consumption,
-                                        json_values.total_data.generated,
+                                    draw_totals_and_weather(
+                                        &mut display,
+                                        &json_values,
+                                        &time_format,
                                     )?;
-                                    // this only needs to be updated every hour
-                                    let sunrise = json_values
-                                        .weather
-                                        .daily
-                                        .sunrise
-                                        .get(0)
-                                        .ok_or(anyhow!("error value not present"))?;
-                                    let sunset = json_values
-                                        .weather
-                                        .daily
-                                        .sunset
-                                        .get(0)
-                                        .ok_or(anyhow!("error value not present"))?;
-                                    display.update_sun_data(sunrise, sunset)?;
-                                    display.update_weather_data(json_values.weather.hourly)?;
                                     flushed = false;
                                 }
-
-                                epd.update_new_frame(
-                                    &mut driver,
-                                    display.buffer(),
-                                    &mut delay::Ets,
-                                )?;
-                                epd.display_new_frame(&mut driver, &mut delay::Ets)?;
-                                epd.update_old_frame(
-                                    &mut driver,
-                                    display.buffer(),
-                                    &mut delay::Ets,
+                                if server_totals.is_none() {
+                                    display.update_total_display(
+                                        &energy::format_kwh(consumption),
+                                        &energy::format_kwh(generated),
+                                    )?;
+                                }
+                                display.draw_co2(
+                                    &co2::format_mass(co2.today),
+                                    &format!(
+                                        "{} {}",
+                                        co2::total_label(config.language),
+                                        co2::format_mass(co2.total)
+                                    ),
                                 )?;
+                                if config.tariff.is_some() {
+                                    display.draw_costs(
+                                        &tariff::format_money(costs.earnings, "+"),
+                                        &tariff::format_money(costs.cost, "-"),
+                                        &format!(
+                                            "{} {} {}",
+                                            tariff::savings_label(config.language),
+                                            tariff::format_money(costs.savings, ""),
+                                            config.currency
+                                        ),
+                                    )?;
+                                }
+
+                                // the hours are counted back from now, so not before the clock is set
+                                if let (Page::Chart, Some(now)) = (pages.page(), now) {
+                                    draw_chart(
+                                        &mut display,
+                                        &history,
+                                        config.chart_hours,
+                                        now.timestamp().max(0) as u32,
+                                    )?;
+                                }
+                                if pages.page() == Page::Records {
+                                    display.clear_buffer(Color::White);
+                                    draw_records(
+                                        &mut display,
+                                        &peaks,
+                                        &time_format,
+                                        config.language,
+                                    )?;
+                                }
+                                if pages.page() == Page::Periods {
+                                    display.clear_buffer(Color::White);
+                                    draw_periods(
+                                        &mut display,
+                                        &rollups,
+                                        energy.day(),
+                                        config.language,
+                                    )?;
+                                }
+                                // page changes flash the panel too, except in the quiet hours. The
+                                // ghosting is cleared after the message is drawn, so it shows at once
+                                if (page_changed && !refresh.quiet(minute))
+                                    || refresh.full_due(time_now, minute)
+                                {
+                                    panel.full_refresh(display.frame())?;
+                                    refresh.full_done(time_now);
+                                } else {
+                                    quick_refresh(&mut panel, &mut display, &mut refresh)?;
+                                }
 
                                 continue;
                             }
@@ -246,16 +517,7 @@ fn main() -> Result<()> {
                                     "Error decoding message!",
                                     default_text_style,
                                 )?;
-                                epd.update_and_display_frame(
-                                    &mut driver,
-                                    display.buffer(),
-                                    &mut delay::Ets,
-                                )?;
-                                epd.update_old_frame(
-                                    &mut driver,
-                                    display.buffer(),
-                                    &mut delay::Ets,
-                                )?;
+                                panel.full_refresh(display.frame())?;
                                 // sleep for 12s to reduce power consumption
                                 // still todo
 
@@ -264,6 +526,32 @@ fn main() -> Result<()> {
                         }
                     }
                 },
+                Err(e) if is_read_timeout(&e) => {
+                    if warning.is_some() || !pages.page().shows_dashboard() {
+                        continue;
+                    }
+                    let mut changed = false;
+                    // the clock keeps running while the server is quiet
+                    if let Some(now) = clock.now() {
+                        let time = time_format.time(now);
+                        if time != shown_clock {
+                            display.draw_clock(&time)?;
+                            shown_clock = time;
+                            changed = true;
+                        }
+                    }
+                    // nothing received for a while, mark the values once they are too old
+                    if freshness.check(SystemTime::now()) == Some(true) {
+                        log::warn!("No new data, marking values as stale");
+                        display.mark_stale(&update_time)?;
+                        changed = true;
+                    }
+                    // held back changes are sent once the server is quiet
+                    if changed || refresh.pending() {
+                        quick_refresh(&mut panel, &mut display, &mut refresh)?;
+                    }
+                    continue;
+                }
                 Err(e) => {
                     println!("Error :{:?}", e);
                     break 'inner;
@@ -278,7 +566,7 @@ fn main() -> Result<()> {
             default_text_style,
         )
         .draw(&mut display)?;
-        epd.update_and_display_frame(&mut driver, display.buffer(), &mut delay::Ets)?;
+        panel.full_refresh(display.frame())?;
 
         continue;
     }
@@ -296,7 +584,222 @@ fn main() -> Result<()> {
         default_text_style,
     )
     .draw(&mut display)?;
-    epd.update_and_display_frame(&mut driver, display.buffer(), &mut delay::Ets)?;
+    panel.full_refresh(display.frame())?;
+
+    Ok(())
+}
+
+// the scheduler counts what was sent to decide when ghosting is likely
+fn quick_refresh(
+    panel: &mut Panel<'_>,
+    display: &mut DisplayBoxed,
+    refresh: &mut RefreshScheduler,
+) -> Result<()> {
+    let now = Instant::now();
+    let frame = display.frame();
+    if !refresh.quick_allowed(&frame.dirty, now) {
+        return Ok(());
+    }
+    refresh.quick_done(&frame.dirty, now);
+    panel.quick_refresh(frame)
+}
+
+// the numbers in and around the circles, the clock and the connections between the circles
+fn draw_power_values(
+    display: &mut DisplayBoxed,
+    values: &types::NewUiStruct,
+    clock_text: &str,
+    low_battery: f32,
+) -> Result<()> {
+    display.hide_banner()?;
+    let grid_import = !values.gui_grid_pow.starts_with("-")
+        && parse_number(values.gui_grid_pow).is_some_and(|power| power != 0.0);
+    let soc_low =
+        parse_number(values.gui_bat_data_fuel_charge).is_some_and(|soc| soc < low_battery);
+    display.highlight_values(grid_import, soc_low);
+    display.draw_text(
+        values.gui_house_pow,
+        &match values.gui_bat_data_power.contains("-") {
+            // meaning the battery is being charged
+            false => {
+                format!("+{}", values.gui_bat_data_fuel_charge)
+            }
+            // meaning battery is being discharged
+            true => {
+                format!("-{}", values.gui_bat_data_fuel_charge)
+            }
+        },
+        values.gui_inverter_power,
+        &match values.gui_grid_pow.starts_with("-") {
+            true => format!("{}", values.gui_grid_pow),
+            false => format!("+{}", values.gui_grid_pow),
+        },
+        clock_text,
+    )?;
+    // to the house always active
+    let mut connections = vec![display::ConnectionDirection::Top(true)];
+
+    // will rework the conditions in the future
+
+    if values.gui_bat_data_power.starts_with("-") && values.gui_bat_data_power != "0.00" {
+        // from the battery since it is being discharged
+        connections.push(display::ConnectionDirection::Bottom(false));
+    } else if !values.gui_bat_data_power.starts_with("-") && values.gui_bat_data_power != "0.00" {
+        // to the battery since it is being charged
+        connections.push(display::ConnectionDirection::Bottom(true));
+    }
+
+    // power send to the grid
+    if values.gui_grid_pow.starts_with("-") && values.gui_grid_pow != "-0.00" {
+        connections.push(display::ConnectionDirection::Right(true));
+    } else if !values.gui_grid_pow.starts_with("-") && values.gui_grid_pow != "0.00" {
+        // power taken from the grid
+        connections.push(display::ConnectionDirection::Right(false));
+    }
+
+    if values.gui_inverter_power != "0.00" && !values.gui_inverter_power.starts_with("-") {
+        connections.push(display::ConnectionDirection::Left(false));
+    }
+    display.draw_connections(&connections)?;
+    Ok(())
+}
 
+fn draw_totals_and_weather(
+    display: &mut DisplayBoxed,
+    values: &types::NewUiStruct,
+    time_format: &TimeFormat,
+) -> Result<()> {
+    display.update_total_display(values.total_data.consumption, values.total_data.generated)?;
+    // this only needs to be updated every hour
+    let sunrise = values
+        .weather
+        .daily
+        .sunrise
+        .get(0)
+        .ok_or(anyhow!("error value not present"))?;
+    let sunset = values
+        .weather
+        .daily
+        .sunset
+        .get(0)
+        .ok_or(anyhow!("error value not present"))?;
+    display.update_sun_data(
+        &time_format.timestamp(sunrise),
+        &time_format.timestamp(sunset),
+    )?;
+    let hour_labels: Vec<String> = values
+        .weather
+        .hourly
+        .time
+        .iter()
+        .map(|time| time_format.hour_timestamp(time))
+        .collect();
+    display.update_weather_data(values.weather.hourly.clone(), &hour_labels)?;
     Ok(())
 }
+
+// the last known values, from before a disconnect or restored from flash after a reboot
+fn draw_last_known(
+    display: &mut DisplayBoxed,
+    message: &str,
+    time_format: &TimeFormat,
+    language: Language,
+    restored: bool,
+    low_battery: f32,
+) -> Result<()> {
+    let values = serde_json::from_str::<types::NewUiStruct>(message)?;
+    let as_of = time_format.timestamp(values.ts);
+    draw_power_values(display, &values, &as_of, low_battery)?;
+    draw_totals_and_weather(display, &values, time_format)?;
+    display.draw_status(SystemState::parse(values.stat_state).0.label(language))?;
+    match restored {
+        true => display.mark_restored(&as_of),
+        false => display.mark_stale(&as_of),
+    }
+}
+
+// the last hours of the history, the unfinished interval is left out
+fn draw_chart(
+    display: &mut DisplayBoxed,
+    history: &History<HISTORY_LEN>,
+    hours: u32,
+    now: u32,
+) -> Result<()> {
+    let from = now.saturating_sub(hours * 3600);
+    let (pv, house): (Vec<f32>, Vec<f32>) = history
+        .since(from)
+        .map(|sample| (sample.pv, sample.house))
+        .unzip();
+    display.draw_chart(&format!("{}h", hours), &pv, &house)
+}
+
+// today's peaks with their time, the all time ones with their date
+fn draw_records(
+    display: &mut DisplayBoxed,
+    peaks: &Peaks,
+    time_format: &TimeFormat,
+    language: Language,
+) -> Result<()> {
+    let rows: Vec<(&str, String, String)> = peaks::RECORDS
+        .iter()
+        .map(|record| {
+            let today = match peaks.today.get(*record) {
+                Some(peak) => format!(
+                    "{} {}",
+                    record.format(peak.value),
+                    time_format.unix_time(peak.timestamp)
+                ),
+                None => "--".to_string(),
+            };
+            let all_time = match peaks.all_time.get(*record) {
+                Some(peak) => format!(
+                    "{} {}",
+                    record.format(peak.value),
+                    time_format.unix_date(peak.timestamp)
+                ),
+                None => "--".to_string(),
+            };
+            (record.label(language), today, all_time)
+        })
+        .collect();
+    let rows: Vec<(&str, &str, &str)> = rows
+        .iter()
+        .map(|(label, today, all_time)| (*label, today.as_str(), all_time.as_str()))
+        .collect();
+    let (title, today, all_time) = peaks::page_labels(language);
+    display.display_records_page(title, (today, all_time), &rows)
+}
+
+// the running day, week and month next to the ones before, "--" until the clock was set
+fn draw_periods(
+    display: &mut DisplayBoxed,
+    rollups: &Rollups,
+    today: Option<DayEnergy>,
+    language: Language,
+) -> Result<()> {
+    let rows: Vec<(&str, String, String)> = rollup::PERIODS
+        .iter()
+        .map(|period| {
+            let (current, previous) = match today {
+                Some(today) => (
+                    rollups.current(*period, &today).format(),
+                    rollups
+                        .previous(*period, today.date)
+                        .map(|rollup| rollup.format()),
+                ),
+                None => ("--".to_string(), None),
+            };
+            (
+                period.label(language),
+                current,
+                previous.unwrap_or_else(|| "--".to_string()),
+            )
+        })
+        .collect();
+    let rows: Vec<(&str, &str, &str)> = rows
+        .iter()
+        .map(|(label, current, previous)| (*label, current.as_str(), previous.as_str()))
+        .collect();
+    let (title, current, previous) = rollup::page_labels(language);
+    display.display_records_page(title, (current, previous), &rows)
+}
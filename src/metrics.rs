@@ -0,0 +1,150 @@
+use crate::energy::EnergyTotals;
+use crate::state::Language;
+use crate::types::PowerValues;
+
+// below this everything is treated as zero, the values are rounded to 10W anyway
+const MIN_POWER: f32 = 0.01;
+
+// share of the consumption covered without the grid, None without any consumption
+pub fn self_sufficiency(consumption: f32, grid_import: f32) -> Option<f32> {
+    if consumption < MIN_POWER {
+        return None;
+    }
+    Some(((consumption - grid_import) / consumption).clamp(0.0, 1.0))
+}
+
+// share of the production used on site, None without any production (at night)
+pub fn self_consumption(production: f32, grid_export: f32) -> Option<f32> {
+    if production < MIN_POWER {
+        return None;
+    }
+    Some(((production - grid_export) / production).clamp(0.0, 1.0))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metrics {
+    pub self_sufficiency: Option<f32>,
+    pub self_consumption: Option<f32>,
+}
+
+impl Metrics {
+    pub fn live(power: &PowerValues) -> Self {
+        Metrics {
+            self_sufficiency: self_sufficiency(power.house, power.grid_import()),
+            self_consumption: self_consumption(power.pv, power.grid_export()),
+        }
+    }
+    // consumption and generated come from total_data when the server sends them,
+    // the grid energy is always counted locally
+    pub fn daily(consumption_kwh: f32, generated_kwh: f32, energy: &EnergyTotals) -> Self {
+        Metrics {
+            self_sufficiency: self_sufficiency(consumption_kwh, energy.grid_import),
+            self_consumption: self_consumption(generated_kwh, energy.grid_export),
+        }
+    }
+}
+
+// labels for the metrics widget
+pub fn labels(language: Language) -> (&'static str, &'static str) {
+    match language {
+        Language::En => ("SS", "SC"),
+        Language::De => ("AU", "EV"),
+    }
+}
+
+// "85" or "--" when there is nothing to compare against
+pub fn format_percent(value: Option<f32>) -> String {
+    match value {
+        Some(value) => format!("{:.0}", value * 100.0),
+        None => "--".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_sufficiency_is_the_share_not_imported() {
+        assert_eq!(self_sufficiency(2.0, 0.5), Some(0.75));
+        assert_eq!(self_sufficiency(2.0, 0.0), Some(1.0));
+        assert_eq!(self_sufficiency(2.0, 3.0), Some(0.0));
+        assert_eq!(self_sufficiency(0.0, 0.5), None);
+        assert_eq!(self_sufficiency(0.005, 0.0), None);
+    }
+
+    #[test]
+    fn self_consumption_is_the_share_not_exported() {
+        assert_eq!(self_consumption(4.0, 1.0), Some(0.75));
+        assert_eq!(self_consumption(4.0, 0.0), Some(1.0));
+        assert_eq!(self_consumption(4.0, 5.0), Some(0.0));
+        assert_eq!(self_consumption(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn live_metrics_follow_the_grid_direction() {
+        let importing = PowerValues {
+            house: 2.0,
+            pv: 1.0,
+            battery: 0.0,
+            grid: 1.0,
+        };
+        assert_eq!(
+            Metrics::live(&importing),
+            Metrics {
+                self_sufficiency: Some(0.5),
+                self_consumption: Some(1.0),
+            }
+        );
+        let exporting = PowerValues {
+            house: 1.0,
+            pv: 4.0,
+            battery: 1.0,
+            grid: -2.0,
+        };
+        assert_eq!(
+            Metrics::live(&exporting),
+            Metrics {
+                self_sufficiency: Some(1.0),
+                self_consumption: Some(0.5),
+            }
+        );
+        // at night
+        let night = PowerValues {
+            house: 0.5,
+            grid: 0.5,
+            ..PowerValues::default()
+        };
+        assert_eq!(Metrics::live(&night).self_consumption, None);
+    }
+
+    #[test]
+    fn daily_metrics_take_the_totals_and_the_local_grid_energy() {
+        let energy = EnergyTotals {
+            consumption: 5.0,
+            generation: 4.0,
+            grid_import: 4.0,
+            grid_export: 2.0,
+            ..EnergyTotals::default()
+        };
+        // the server totals cover the whole day, the local counters only since the reboot
+        assert_eq!(
+            Metrics::daily(10.0, 8.0, &energy),
+            Metrics {
+                self_sufficiency: Some(0.6),
+                self_consumption: Some(0.75),
+            }
+        );
+        assert_eq!(
+            Metrics::daily(0.0, 0.0, &EnergyTotals::default()),
+            Metrics::default()
+        );
+    }
+
+    #[test]
+    fn percent_without_decimals() {
+        assert_eq!(format_percent(Some(0.854)), "85");
+        assert_eq!(format_percent(Some(1.0)), "100");
+        assert_eq!(format_percent(None), "--");
+    }
+}
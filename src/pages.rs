@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Dashboard,
+    // the dashboard with the power history over the totals
+    Chart,
+    Records,
+    // day, week and month next to the period before
+    Periods,
+}
+
+impl Page {
+    pub fn shows_dashboard(&self) -> bool {
+        matches!(self, Page::Dashboard | Page::Chart)
+    }
+}
+
+// alternates between the dashboard and the other pages, each is shown for its own time
+pub struct PageRotation {
+    pages: Vec<(Page, Duration)>,
+    current: usize,
+    since: Instant,
+}
+
+impl PageRotation {
+    // pages with a zero duration are left out
+    pub fn new(pages: &[(Page, Duration)]) -> Self {
+        let mut pages: Vec<(Page, Duration)> = pages
+            .iter()
+            .filter(|(_, duration)| !duration.is_zero())
+            .copied()
+            .collect();
+        if pages.is_empty() {
+            pages.push((Page::Dashboard, Duration::MAX));
+        }
+        PageRotation {
+            pages,
+            current: 0,
+            since: Instant::now(),
+        }
+    }
+    pub fn page(&self) -> Page {
+        self.pages[self.current].0
+    }
+    // returns the new page when it is time to switch
+    pub fn poll(&mut self, now: Instant) -> Option<Page> {
+        if self.pages.len() < 2 || now.duration_since(self.since) < self.pages[self.current].1 {
+            return None;
+        }
+        self.current = (self.current + 1) % self.pages.len();
+        self.since = now;
+        Some(self.page())
+    }
+}
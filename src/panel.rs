@@ -0,0 +1,292 @@
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::Rectangle;
+use epd_waveshare::prelude::*;
+
+// the panel is picked with a cargo feature, without one the 2.9" panel is used
+const SELECTED: usize = cfg!(feature = "panel-2in13") as usize
+    + cfg!(feature = "panel-4in2") as usize
+    + cfg!(feature = "panel-7in5") as usize
+    + cfg!(feature = "panel-2in9bc") as usize
+    + cfg!(feature = "panel-2in13bc") as usize;
+const _: () = assert!(
+    matches!(SELECTED, 0 | 1),
+    "only one of the panel-* features can be enabled"
+);
+
+#[cfg(feature = "panel-2in13")]
+pub mod model {
+    pub use epd_waveshare::epd2in13_v2::{Display2in13 as Buffer, Epd2in13 as Epd, HEIGHT, WIDTH};
+    use epd_waveshare::prelude::DisplayRotation;
+    pub const ROTATION: DisplayRotation = DisplayRotation::Rotate90;
+}
+
+#[cfg(feature = "panel-4in2")]
+pub mod model {
+    pub use epd_waveshare::epd4in2::{Display4in2 as Buffer, Epd4in2 as Epd, HEIGHT, WIDTH};
+    use epd_waveshare::prelude::DisplayRotation;
+    // already landscape
+    pub const ROTATION: DisplayRotation = DisplayRotation::Rotate0;
+}
+
+#[cfg(feature = "panel-7in5")]
+pub mod model {
+    pub use epd_waveshare::epd7in5_v2::{Display7in5 as Buffer, Epd7in5 as Epd, HEIGHT, WIDTH};
+    use epd_waveshare::prelude::DisplayRotation;
+    // already landscape
+    pub const ROTATION: DisplayRotation = DisplayRotation::Rotate0;
+}
+
+#[cfg(feature = "panel-2in9bc")]
+pub mod model {
+    pub use epd_waveshare::epd2in9bc::{Display2in9bc as Buffer, Epd2in9bc as Epd, HEIGHT, WIDTH};
+    use epd_waveshare::prelude::DisplayRotation;
+    pub const ROTATION: DisplayRotation = DisplayRotation::Rotate90;
+}
+
+#[cfg(feature = "panel-2in13bc")]
+pub mod model {
+    pub use epd_waveshare::epd2in13bc::{
+        Display2in13bc as Buffer, Epd2in13bc as Epd, HEIGHT, WIDTH,
+    };
+    use epd_waveshare::prelude::DisplayRotation;
+    pub const ROTATION: DisplayRotation = DisplayRotation::Rotate90;
+}
+
+#[cfg(not(any(
+    feature = "panel-2in13",
+    feature = "panel-4in2",
+    feature = "panel-7in5",
+    feature = "panel-2in9bc",
+    feature = "panel-2in13bc"
+)))]
+pub mod model {
+    pub use epd_waveshare::epd2in9_v2::{Display2in9 as Buffer, Epd2in9 as Epd, HEIGHT, WIDTH};
+    use epd_waveshare::prelude::DisplayRotation;
+    pub const ROTATION: DisplayRotation = DisplayRotation::Rotate90;
+}
+
+pub use model::{Buffer, ROTATION};
+
+// the other panels redraw every pixel on each update
+pub const PARTIAL_REFRESH: bool = !cfg!(any(
+    feature = "panel-2in13",
+    feature = "panel-7in5",
+    feature = "panel-2in9bc",
+    feature = "panel-2in13bc"
+));
+
+// the red layer is only drawn on these
+pub const THREE_COLOR: bool = cfg!(any(feature = "panel-2in9bc", feature = "panel-2in13bc"));
+
+// width and height the dashboard is drawn with, after the rotation
+pub fn size() -> Size {
+    match ROTATION {
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+            Size::new(model::HEIGHT, model::WIDTH)
+        }
+        _ => Size::new(model::WIDTH, model::HEIGHT),
+    }
+}
+
+// the buffers that are sent to the panel, the chromatic one only exists on three color panels
+pub struct Frame<'a> {
+    pub black: &'a [u8],
+    pub chromatic: Option<&'a [u8]>,
+    // what was drawn since the last refresh, in screen coordinates
+    pub dirty: Vec<Rectangle>,
+}
+
+// the e-paper controller on the spi bus, only on the esp32
+#[cfg(target_os = "espidf")]
+mod driver {
+    use epd_waveshare::prelude::*;
+    use esp_idf_hal::delay::Ets;
+    use esp_idf_hal::gpio::{Gpio10, Gpio17, Gpio18, Gpio21, Input, Output, PinDriver};
+    use esp_idf_hal::spi::{SpiDeviceDriver, SpiDriver};
+
+    use super::{model, Frame};
+
+    type Spi<'a> = SpiDeviceDriver<'a, SpiDriver<'a>>;
+
+    pub type Epd<'a> = model::Epd<
+        Spi<'a>,
+        PinDriver<'a, Gpio21, Output>,
+        PinDriver<'a, Gpio10, Input>,
+        PinDriver<'a, Gpio18, Output>,
+        PinDriver<'a, Gpio17, Output>,
+        Ets,
+    >;
+
+    // the e-paper controller with its spi device
+    pub struct Panel<'a> {
+        epd: Epd<'a>,
+        driver: Spi<'a>,
+    }
+
+    impl<'a> Panel<'a> {
+        pub fn new(epd: Epd<'a>, driver: Spi<'a>) -> Self {
+            Panel { epd, driver }
+        }
+        pub fn clear_frame(&mut self) -> anyhow::Result<()> {
+            self.epd.clear_frame(&mut self.driver, &mut Ets)?;
+            Ok(())
+        }
+    }
+
+    // the 2.9" and 4.2" panels can redraw only the changed pixels
+    #[cfg(not(any(
+        feature = "panel-2in13",
+        feature = "panel-7in5",
+        feature = "panel-2in9bc",
+        feature = "panel-2in13bc"
+    )))]
+    mod quick {
+        use embedded_graphics::primitives::Rectangle;
+
+        use super::*;
+        use crate::panel::ROTATION;
+
+        impl<'a> Panel<'a> {
+            // redraws every pixel, the panel flashes but ghosting is removed
+            pub fn full_refresh(&mut self, frame: Frame) -> anyhow::Result<()> {
+                self.epd
+                    .update_and_display_frame(&mut self.driver, frame.black, &mut Ets)?;
+                // the quick refresh compares against this frame
+                self.epd
+                    .update_old_frame(&mut self.driver, frame.black, &mut Ets)?;
+                Ok(())
+            }
+            // only the dirty regions are sent, nothing happens when nothing was drawn
+            pub fn quick_refresh(&mut self, frame: Frame) -> anyhow::Result<()> {
+                if frame.dirty.is_empty() {
+                    return Ok(());
+                }
+                let windows: Vec<Window> = frame.dirty.iter().map(Window::new).collect();
+                for window in windows.iter() {
+                    self.epd.update_partial_new_frame(
+                        &mut self.driver,
+                        &mut Ets,
+                        &window.bytes(frame.black),
+                        window.x,
+                        window.y,
+                        window.width,
+                        window.height,
+                    )?;
+                }
+                self.epd.display_new_frame(&mut self.driver, &mut Ets)?;
+                for window in windows.iter() {
+                    self.epd.update_partial_old_frame(
+                        &mut self.driver,
+                        &mut Ets,
+                        &window.bytes(frame.black),
+                        window.x,
+                        window.y,
+                        window.width,
+                        window.height,
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        // a region in the orientation of the controller, x and width are widened to whole bytes
+        struct Window {
+            x: u32,
+            y: u32,
+            width: u32,
+            height: u32,
+        }
+
+        impl Window {
+            // same mapping as the rotation of the buffer
+            fn new(area: &Rectangle) -> Self {
+                // the dirty regions are clipped to the screen, outside of it the mapping underflows
+                let screen = crate::panel::size();
+                debug_assert!(
+                    area.top_left.x >= 0
+                        && area.top_left.y >= 0
+                        && area.top_left.x as u32 + area.size.width <= screen.width
+                        && area.top_left.y as u32 + area.size.height <= screen.height,
+                    "{:?} is outside of the screen",
+                    area
+                );
+                let (x, y) = (area.top_left.x as u32, area.top_left.y as u32);
+                let (width, height) = (area.size.width, area.size.height);
+                let (x, y, width, height) = match ROTATION {
+                    DisplayRotation::Rotate0 => (x, y, width, height),
+                    DisplayRotation::Rotate90 => (model::WIDTH - y - height, x, height, width),
+                    DisplayRotation::Rotate180 => (
+                        model::WIDTH - x - width,
+                        model::HEIGHT - y - height,
+                        width,
+                        height,
+                    ),
+                    DisplayRotation::Rotate270 => (y, model::HEIGHT - x - width, height, width),
+                };
+                let start = x / 8 * 8;
+                let end = ((x + width).div_ceil(8) * 8).min(row_bytes() * 8);
+                Window {
+                    x: start,
+                    y,
+                    width: end - start,
+                    height,
+                }
+            }
+            // the part of the buffer inside the window, row by row
+            fn bytes(&self, buffer: &[u8]) -> Vec<u8> {
+                let (start, end) = (self.x / 8, (self.x + self.width) / 8);
+                (self.y..self.y + self.height)
+                    .flat_map(|row| {
+                        let row = (row * row_bytes()) as usize;
+                        buffer[row + start as usize..row + end as usize]
+                            .iter()
+                            .copied()
+                    })
+                    .collect()
+            }
+        }
+
+        fn row_bytes() -> u32 {
+            model::WIDTH.div_ceil(8)
+        }
+    }
+
+    #[cfg(any(feature = "panel-2in13", feature = "panel-7in5"))]
+    impl<'a> Panel<'a> {
+        pub fn full_refresh(&mut self, frame: Frame) -> anyhow::Result<()> {
+            self.epd
+                .update_and_display_frame(&mut self.driver, frame.black, &mut Ets)?;
+            Ok(())
+        }
+        // these panels always redraw every pixel, the scheduler keeps the updates apart
+        pub fn quick_refresh(&mut self, frame: Frame) -> anyhow::Result<()> {
+            self.full_refresh(frame)
+        }
+    }
+
+    #[cfg(any(feature = "panel-2in9bc", feature = "panel-2in13bc"))]
+    impl<'a> Panel<'a> {
+        pub fn full_refresh(&mut self, frame: Frame) -> anyhow::Result<()> {
+            match frame.chromatic {
+                Some(chromatic) => self.epd.update_color_frame(
+                    &mut self.driver,
+                    &mut Ets,
+                    frame.black,
+                    chromatic,
+                )?,
+                None => self
+                    .epd
+                    .update_frame(&mut self.driver, frame.black, &mut Ets)?,
+            }
+            self.epd.display_frame(&mut self.driver, &mut Ets)?;
+            Ok(())
+        }
+        // three color panels have no partial refresh, the scheduler keeps the updates apart
+        pub fn quick_refresh(&mut self, frame: Frame) -> anyhow::Result<()> {
+            self.full_refresh(frame)
+        }
+    }
+}
+
+#[cfg(target_os = "espidf")]
+pub use driver::{Epd, Panel};
@@ -0,0 +1,131 @@
+use crate::state::Language;
+use crate::types::PowerValues;
+
+// a value and the unix seconds it was seen at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Peak {
+    pub value: f32,
+    pub timestamp: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Record {
+    Pv,
+    House,
+    GridImport,
+    GridExport,
+    // the only minimum
+    MinSoc,
+}
+
+pub const RECORDS: [Record; 5] = [
+    Record::Pv,
+    Record::House,
+    Record::GridImport,
+    Record::GridExport,
+    Record::MinSoc,
+];
+
+impl Record {
+    fn is_higher_better(&self) -> bool {
+        !matches!(self, Record::MinSoc)
+    }
+    // at most 10 characters for the records page
+    pub fn label(&self, language: Language) -> &'static str {
+        match language {
+            Language::En => match self {
+                Record::Pv => "PV max",
+                Record::House => "Load max",
+                Record::GridImport => "Import max",
+                Record::GridExport => "Export max",
+                Record::MinSoc => "SoC min",
+            },
+            Language::De => match self {
+                Record::Pv => "PV max",
+                Record::House => "Last max",
+                Record::GridImport => "Bezug max",
+                Record::GridExport => "Einsp. max",
+                Record::MinSoc => "SoC min",
+            },
+        }
+    }
+    // kW with two decimals, the soc in percent
+    pub fn format(&self, value: f32) -> String {
+        match self {
+            Record::MinSoc => format!("{:.0}%", value),
+            _ => format!("{:.2}kW", value),
+        }
+    }
+}
+
+// one peak per record, in the order of RECORDS
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PeakSet([Option<Peak>; 5]);
+
+impl PeakSet {
+    pub fn get(&self, record: Record) -> Option<Peak> {
+        self.0[record as usize]
+    }
+    pub fn set(&mut self, record: Record, peak: Option<Peak>) {
+        self.0[record as usize] = peak;
+    }
+    // returns true when a new peak was set
+    fn update(&mut self, record: Record, value: f32, timestamp: u32) -> bool {
+        let new = match self.get(record) {
+            Some(peak) if record.is_higher_better() => value > peak.value,
+            Some(peak) => value < peak.value,
+            None => true,
+        };
+        if new {
+            self.set(record, Some(Peak { value, timestamp }));
+        }
+        new
+    }
+}
+
+// the peaks of the current local day and of all time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Peaks {
+    pub today: PeakSet,
+    pub all_time: PeakSet,
+}
+
+impl Peaks {
+    // only called with a synced clock, so the timestamps can be shown
+    pub fn update(&mut self, power: &PowerValues, soc: Option<f32>, timestamp: u32) -> bool {
+        let values = [
+            Some(power.pv),
+            Some(power.house),
+            Some(power.grid_import()),
+            Some(power.grid_export()),
+            soc,
+        ];
+        let mut changed = false;
+        for (record, value) in RECORDS.iter().zip(values) {
+            if let Some(value) = value {
+                changed |= self.today.update(*record, value, timestamp);
+                changed |= self.all_time.update(*record, value, timestamp);
+            }
+        }
+        changed
+    }
+    // drops the peaks of earlier days, also the ones restored from before a reboot
+    pub fn start_day(&mut self, day_start: u32) {
+        for record in RECORDS {
+            if self
+                .today
+                .get(record)
+                .is_some_and(|peak| peak.timestamp < day_start)
+            {
+                self.today.set(record, None);
+            }
+        }
+    }
+}
+
+pub fn page_labels(language: Language) -> (&'static str, &'static str, &'static str) {
+    match language {
+        Language::En => ("Records", "today", "all time"),
+        Language::De => ("Rekorde", "heute", "gesamt"),
+    }
+}
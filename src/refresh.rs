@@ -0,0 +1,208 @@
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::Rectangle;
+use std::time::{Duration, Instant};
+
+use crate::clock::TimeWindow;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefreshThresholds {
+    // quick refreshes after which ghosting is likely
+    pub max_quick: u32,
+    // changed area after which ghosting is likely, in whole screens
+    pub max_area: f32,
+    // full refreshes flash the panel, so they are never closer together than this
+    pub min_interval: Duration,
+    // no full refreshes in this local time window
+    pub quiet_hours: Option<TimeWindow>,
+    // panels without partial refresh flash on every update, so they are updated at most this often
+    pub slow_interval: Duration,
+}
+
+// decides when the quick refreshes left enough ghosting behind to flash the whole panel
+pub struct RefreshScheduler {
+    thresholds: RefreshThresholds,
+    screen_area: u64,
+    // without partial refresh every update redraws the whole panel, so there is no ghosting
+    partial: bool,
+    // drawn but held back from a panel without partial refresh
+    pending: bool,
+    last_quick: Instant,
+    quick: u32,
+    // changed pixels since the last full refresh
+    area: u64,
+    last_full: Instant,
+}
+
+impl RefreshScheduler {
+    pub fn new(thresholds: RefreshThresholds, screen: Size, partial: bool, now: Instant) -> Self {
+        RefreshScheduler {
+            thresholds,
+            screen_area: screen.width as u64 * screen.height as u64,
+            partial,
+            pending: false,
+            last_quick: now,
+            quick: 0,
+            area: 0,
+            last_full: now,
+        }
+    }
+    // panels without partial refresh only get the changes of several messages at once
+    pub fn quick_allowed(&mut self, regions: &[Rectangle], now: Instant) -> bool {
+        if self.partial {
+            return true;
+        }
+        self.pending |= !regions.is_empty();
+        self.pending && now.duration_since(self.last_quick) >= self.thresholds.slow_interval
+    }
+    // changes that still have to be sent once the interval passed
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+    // the regions a quick refresh sent to the panel, an empty refresh leaves no ghosting
+    pub fn quick_done(&mut self, regions: &[Rectangle], now: Instant) {
+        self.pending = false;
+        self.last_quick = now;
+        if regions.is_empty() {
+            return;
+        }
+        self.quick += 1;
+        self.area += regions
+            .iter()
+            .map(|region| region.size.width as u64 * region.size.height as u64)
+            .sum::<u64>();
+    }
+    pub fn full_done(&mut self, now: Instant) {
+        self.quick = 0;
+        self.area = 0;
+        self.pending = false;
+        self.last_full = now;
+        self.last_quick = now;
+    }
+    // the minute of the day is None while the clock is not set, quiet hours can't apply then
+    pub fn quiet(&self, minute: Option<u16>) -> bool {
+        match (self.thresholds.quiet_hours, minute) {
+            (Some(quiet_hours), Some(minute)) => quiet_hours.contains(minute),
+            _ => false,
+        }
+    }
+    pub fn full_due(&self, now: Instant, minute: Option<u16>) -> bool {
+        let thresholds = &self.thresholds;
+        if !self.partial || now.duration_since(self.last_full) < thresholds.min_interval {
+            return false;
+        }
+        if self.quiet(minute) {
+            return false;
+        }
+        self.quick >= thresholds.max_quick
+            || self.area as f32 >= thresholds.max_area * self.screen_area as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock;
+    use embedded_graphics::prelude::Point;
+
+    const SCREEN: Size = Size::new(100, 100);
+
+    fn thresholds() -> RefreshThresholds {
+        RefreshThresholds {
+            max_quick: 3,
+            max_area: 1.0,
+            min_interval: Duration::from_secs(120),
+            quiet_hours: None,
+            slow_interval: Duration::from_secs(30),
+        }
+    }
+
+    fn region(size: u32) -> [Rectangle; 1] {
+        [Rectangle::new(Point::zero(), Size::new(size, size))]
+    }
+
+    fn secs(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn partial_panels_refresh_every_change() {
+        let start = Instant::now();
+        let mut refresh = RefreshScheduler::new(thresholds(), SCREEN, true, start);
+        assert!(refresh.quick_allowed(&region(10), start));
+        refresh.quick_done(&region(10), start);
+        assert!(refresh.quick_allowed(&region(10), start));
+        assert!(!refresh.pending());
+    }
+
+    #[test]
+    fn full_refresh_after_enough_quick_refreshes_or_area() {
+        let start = Instant::now();
+        let mut refresh = RefreshScheduler::new(thresholds(), SCREEN, true, start);
+        for _ in 0..3 {
+            refresh.quick_done(&region(10), start);
+        }
+        assert!(!refresh.full_due(secs(start, 60), None));
+        assert!(refresh.full_due(secs(start, 120), None));
+        refresh.full_done(secs(start, 120));
+        assert!(!refresh.full_due(secs(start, 1000), None));
+        refresh.quick_done(&region(100), secs(start, 1000));
+        assert!(refresh.full_due(secs(start, 1000), None));
+    }
+
+    #[test]
+    fn slow_panels_collect_the_changes() {
+        let start = Instant::now();
+        let mut refresh = RefreshScheduler::new(thresholds(), SCREEN, false, start);
+        assert!(!refresh.quick_allowed(&region(10), secs(start, 10)));
+        assert!(refresh.pending());
+        // nothing new drawn, the held back changes are still sent once the interval passed
+        assert!(!refresh.quick_allowed(&[], secs(start, 29)));
+        assert!(refresh.quick_allowed(&[], secs(start, 30)));
+        refresh.quick_done(&[], secs(start, 30));
+        assert!(!refresh.pending());
+        assert!(!refresh.quick_allowed(&[], secs(start, 100)));
+        assert!(refresh.quick_allowed(&region(10), secs(start, 100)));
+    }
+
+    #[test]
+    fn slow_panels_need_no_full_refresh() {
+        let start = Instant::now();
+        let mut refresh = RefreshScheduler::new(thresholds(), SCREEN, false, start);
+        refresh.quick_done(&region(100), secs(start, 30));
+        assert!(!refresh.full_due(secs(start, 1000), None));
+    }
+
+    #[test]
+    fn empty_quick_refreshes_are_not_counted() {
+        let start = Instant::now();
+        let mut refresh = RefreshScheduler::new(thresholds(), SCREEN, true, start);
+        for _ in 0..10 {
+            refresh.quick_done(&[], start);
+        }
+        assert!(!refresh.full_due(secs(start, 1000), None));
+        for _ in 0..3 {
+            refresh.quick_done(&region(10), start);
+        }
+        assert!(refresh.full_due(secs(start, 1000), None));
+    }
+
+    #[test]
+    fn no_full_refresh_in_the_quiet_hours() {
+        let start = Instant::now();
+        let thresholds = RefreshThresholds {
+            quiet_hours: Some(clock::parse_window("22:00-06:00").unwrap()),
+            ..thresholds()
+        };
+        let mut refresh = RefreshScheduler::new(thresholds, SCREEN, true, start);
+        refresh.quick_done(&region(100), start);
+        let later = secs(start, 500);
+        assert!(refresh.quiet(Some(23 * 60)));
+        assert!(!refresh.full_due(later, Some(23 * 60)));
+        assert!(!refresh.full_due(later, Some(60)));
+        assert!(!refresh.quiet(Some(6 * 60)));
+        assert!(refresh.full_due(later, Some(6 * 60)));
+        // the clock is not set yet
+        assert!(!refresh.quiet(None));
+        assert!(refresh.full_due(later, None));
+    }
+}
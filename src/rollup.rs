@@ -0,0 +1,344 @@
+use chrono::{Datelike, Days, NaiveDate};
+use heapless::HistoryBuffer;
+
+use crate::energy::{self, DayEnergy};
+use crate::metrics;
+use crate::state::Language;
+
+// closed periods that are kept, the running one comes on top
+pub const DAYS_LEN: usize = 31;
+pub const WEEKS_LEN: usize = 12;
+pub const MONTHS_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    // starts on monday
+    Week,
+    Month,
+}
+
+// the rows of the periods page
+pub const PERIODS: [Period; 3] = [Period::Day, Period::Week, Period::Month];
+
+impl Period {
+    // at most 10 characters for the periods page
+    pub fn label(&self, language: Language) -> &'static str {
+        match language {
+            Language::En => match self {
+                Period::Day => "Day",
+                Period::Week => "Week",
+                Period::Month => "Month",
+            },
+            Language::De => match self {
+                Period::Day => "Tag",
+                Period::Week => "Woche",
+                Period::Month => "Monat",
+            },
+        }
+    }
+    // first local day of the period containing date
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Period::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+    // start of the period before the one containing date
+    pub fn previous_start(&self, date: NaiveDate) -> NaiveDate {
+        self.start(self.start(date) - Days::new(1))
+    }
+}
+
+// energy of one period in kWh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rollup {
+    pub start: NaiveDate,
+    // the last day that was added
+    pub end: NaiveDate,
+    pub consumption: f32,
+    pub generation: f32,
+    pub grid_import: f32,
+    pub grid_export: f32,
+    // false when a day is missing or was only partly counted, after a reboot
+    pub complete: bool,
+}
+
+impl Rollup {
+    fn new(period: Period, day: &DayEnergy) -> Self {
+        let start = period.start(day.date);
+        Rollup {
+            start,
+            end: day.date,
+            consumption: day.energy.consumption,
+            generation: day.energy.generation,
+            grid_import: day.energy.grid_import,
+            grid_export: day.energy.grid_export,
+            complete: day.complete && start == day.date,
+        }
+    }
+    fn add(&mut self, day: &DayEnergy) {
+        self.consumption += day.energy.consumption;
+        self.generation += day.energy.generation;
+        self.grid_import += day.energy.grid_import;
+        self.grid_export += day.energy.grid_export;
+        self.complete &= day.complete && self.end.succ_opt() == Some(day.date);
+        self.end = day.date;
+    }
+    pub fn autarky(&self) -> Option<f32> {
+        metrics::self_sufficiency(self.consumption, self.grid_import)
+    }
+
+    // "12.3kWh 85%", the consumption and the self-sufficiency
+    pub fn format(&self) -> String {
+        format!(
+            "{}kWh {}%",
+            energy::format_kwh(self.consumption),
+            metrics::format_percent(self.autarky())
+        )
+    }
+}
+
+// the periods of one kind, older ones are overwritten
+pub struct Series<const N: usize> {
+    period: Period,
+    closed: HistoryBuffer<Rollup, N>,
+    // the period the last finished day belongs to
+    open: Option<Rollup>,
+}
+
+impl<const N: usize> Series<N> {
+    fn new(period: Period) -> Self {
+        Series {
+            period,
+            closed: HistoryBuffer::new(),
+            open: None,
+        }
+    }
+    fn add_day(&mut self, day: &DayEnergy) {
+        match self.open.as_mut() {
+            Some(open) if open.start == self.period.start(day.date) => open.add(day),
+            _ => self.restore(Rollup::new(self.period, day)),
+        }
+    }
+    // restored rollups from flash go in oldest first
+    pub fn restore(&mut self, rollup: Rollup) {
+        if let Some(open) = self.open.replace(rollup) {
+            self.closed.write(open);
+        }
+    }
+    // oldest first, the running period included
+    pub fn iter(&self) -> impl Iterator<Item = &Rollup> {
+        self.closed.oldest_ordered().chain(self.open.iter())
+    }
+    pub fn len(&self) -> usize {
+        self.closed.len() + self.open.iter().count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, start: NaiveDate) -> Option<&Rollup> {
+        self.iter().find(|rollup| rollup.start == start)
+    }
+}
+
+// daily, weekly and monthly energy, built from the locally counted days
+pub struct Rollups {
+    pub days: Series<DAYS_LEN>,
+    pub weeks: Series<WEEKS_LEN>,
+    pub months: Series<MONTHS_LEN>,
+}
+
+impl Default for Rollups {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rollups {
+    pub fn new() -> Self {
+        Rollups {
+            days: Series::new(Period::Day),
+            weeks: Series::new(Period::Week),
+            months: Series::new(Period::Month),
+        }
+    }
+    // called with every day the energy counter finished
+    pub fn add_day(&mut self, day: &DayEnergy) {
+        self.days.add_day(day);
+        self.weeks.add_day(day);
+        self.months.add_day(day);
+    }
+    pub fn get(&self, period: Period, start: NaiveDate) -> Option<Rollup> {
+        match period {
+            Period::Day => self.days.get(start),
+            Period::Week => self.weeks.get(start),
+            Period::Month => self.months.get(start),
+        }
+        .copied()
+    }
+    // the running period up to now, today is not part of the stored rollups yet
+    pub fn current(&self, period: Period, today: &DayEnergy) -> Rollup {
+        match self.get(period, period.start(today.date)) {
+            Some(mut rollup) if rollup.end < today.date => {
+                rollup.add(today);
+                rollup
+            }
+            Some(rollup) => rollup,
+            None => Rollup::new(period, today),
+        }
+    }
+    // the period before the running one, to compare against
+    pub fn previous(&self, period: Period, today: NaiveDate) -> Option<Rollup> {
+        self.get(period, period.previous_start(today))
+    }
+}
+
+// title and column headers of the periods page
+pub fn page_labels(language: Language) -> (&'static str, &'static str, &'static str) {
+    match language {
+        Language::En => ("Consumption", "current", "previous"),
+        Language::De => ("Verbrauch", "aktuell", "vorher"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::energy::EnergyTotals;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn day(date: NaiveDate, consumption: f32) -> DayEnergy {
+        DayEnergy {
+            date,
+            energy: EnergyTotals {
+                consumption,
+                grid_import: consumption / 2.0,
+                ..EnergyTotals::default()
+            },
+            complete: true,
+        }
+    }
+
+    #[test]
+    fn weeks_start_on_monday_across_the_new_year() {
+        // wednesday of the first iso week of 2025, which starts in 2024
+        assert_eq!(Period::Week.start(date(2025, 1, 1)), date(2024, 12, 30));
+        assert_eq!(Period::Week.start(date(2024, 12, 30)), date(2024, 12, 30));
+        assert_eq!(Period::Week.start(date(2025, 1, 5)), date(2024, 12, 30));
+        assert_eq!(Period::Week.start(date(2025, 1, 6)), date(2025, 1, 6));
+    }
+
+    #[test]
+    fn previous_start_goes_back_one_period() {
+        assert_eq!(
+            Period::Day.previous_start(date(2024, 3, 1)),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            Period::Week.previous_start(date(2025, 1, 1)),
+            date(2024, 12, 23)
+        );
+        assert_eq!(
+            Period::Month.previous_start(date(2025, 1, 15)),
+            date(2024, 12, 1)
+        );
+        assert_eq!(
+            Period::Month.previous_start(date(2024, 3, 31)),
+            date(2024, 2, 1)
+        );
+    }
+
+    #[test]
+    fn months_and_weeks_roll_over() {
+        let mut rollups = Rollups::new();
+        // sunday to tuesday, over the end of the month
+        for (idx, date) in [date(2024, 3, 31), date(2024, 4, 1), date(2024, 4, 2)]
+            .into_iter()
+            .enumerate()
+        {
+            rollups.add_day(&day(date, idx as f32 + 1.0));
+        }
+        let march = rollups.get(Period::Month, date(2024, 3, 1)).unwrap();
+        assert_eq!(march.consumption, 1.0);
+        // the month wasn't counted from its first day
+        assert!(!march.complete);
+        let april = rollups.get(Period::Month, date(2024, 4, 1)).unwrap();
+        assert_eq!((april.consumption, april.end), (5.0, date(2024, 4, 2)));
+        assert!(april.complete);
+        assert_eq!(
+            rollups
+                .get(Period::Week, date(2024, 3, 25))
+                .unwrap()
+                .consumption,
+            1.0
+        );
+        assert_eq!(
+            rollups
+                .get(Period::Week, date(2024, 4, 1))
+                .unwrap()
+                .consumption,
+            5.0
+        );
+        assert_eq!(rollups.days.len(), 3);
+    }
+
+    #[test]
+    fn a_missing_day_makes_the_period_incomplete() {
+        let mut rollups = Rollups::new();
+        rollups.add_day(&day(date(2024, 4, 1), 1.0));
+        rollups.add_day(&day(date(2024, 4, 3), 1.0));
+        assert!(
+            !rollups
+                .get(Period::Week, date(2024, 4, 1))
+                .unwrap()
+                .complete
+        );
+    }
+
+    #[test]
+    fn current_adds_today_and_previous_is_the_period_before() {
+        let mut rollups = Rollups::new();
+        rollups.add_day(&day(date(2024, 3, 31), 4.0));
+        rollups.add_day(&day(date(2024, 4, 1), 2.0));
+        let today = day(date(2024, 4, 2), 1.0);
+        let month = rollups.current(Period::Month, &today);
+        assert_eq!((month.start, month.consumption), (date(2024, 4, 1), 3.0));
+        assert_eq!(rollups.current(Period::Day, &today).consumption, 1.0);
+        // adding today twice doesn't count it twice
+        rollups.add_day(&today);
+        assert_eq!(rollups.current(Period::Month, &today).consumption, 3.0);
+        let previous = rollups.previous(Period::Month, today.date).unwrap();
+        assert_eq!(previous.consumption, 4.0);
+        // the sunday before belongs to the previous week
+        assert_eq!(
+            rollups
+                .previous(Period::Week, today.date)
+                .unwrap()
+                .consumption,
+            4.0
+        );
+        assert_eq!(rollups.previous(Period::Day, date(2024, 3, 31)), None);
+        assert_eq!(previous.format(), "4.00kWh 50%");
+    }
+
+    #[test]
+    fn series_keep_the_latest_periods() {
+        let mut rollups = Rollups::new();
+        let mut date = date(2024, 1, 1);
+        for _ in 0..40 {
+            rollups.add_day(&day(date, 1.0));
+            date = date.succ_opt().unwrap();
+        }
+        // the closed days and the running one
+        assert_eq!(rollups.days.len(), DAYS_LEN + 1);
+        assert_eq!(
+            rollups.days.iter().next().unwrap().start,
+            NaiveDate::from_ymd_opt(2024, 1, 9).unwrap()
+        );
+    }
+}
@@ -0,0 +1,171 @@
+// keeps track of the sequence numbers the server attaches to every message
+// so we notice dropped messages, replays and server restarts
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceEvent {
+    // the server does not send sequence numbers
+    Untracked,
+    // first numbered message after (re)connecting
+    First(u64),
+    InOrder(u64),
+    // messages between expected and received were lost
+    Gap { expected: u64, received: u64 },
+    // the message was already processed
+    Duplicate(u64),
+    // the server instance changed or its counter started over
+    Restart(u64),
+}
+
+impl SequenceEvent {
+    // after a gap or a restart the cached totals and weather may be out of date
+    pub fn needs_resend(&self) -> bool {
+        matches!(self, SequenceEvent::Gap { .. } | SequenceEvent::Restart(_))
+    }
+}
+
+#[derive(Default)]
+pub struct SequenceTracker {
+    last_seq: Option<u64>,
+    server_id: Option<String>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // called after a reconnect, the next message is treated as the first one
+    pub fn reset(&mut self) {
+        self.last_seq = None;
+    }
+    pub fn track(&mut self, seq: Option<u64>, server_id: Option<&str>) -> SequenceEvent {
+        let seq = match seq {
+            Some(seq) => seq,
+            None => return SequenceEvent::Untracked,
+        };
+
+        let server_changed = match (self.server_id.as_deref(), server_id) {
+            (Some(known), Some(received)) => known != received,
+            _ => false,
+        };
+        if let Some(id) = server_id {
+            if self.server_id.as_deref() != Some(id) {
+                self.server_id = Some(id.to_string());
+            }
+        }
+
+        let event = match self.last_seq {
+            None => match server_changed {
+                true => SequenceEvent::Restart(seq),
+                false => SequenceEvent::First(seq),
+            },
+            Some(_) if server_changed => SequenceEvent::Restart(seq),
+            Some(last) if seq == last => SequenceEvent::Duplicate(seq),
+            Some(last) => match last.checked_add(1) {
+                // the counter can't go any further, so it started over
+                None => SequenceEvent::Restart(seq),
+                Some(next) if seq == next => SequenceEvent::InOrder(seq),
+                Some(next) if seq > next => SequenceEvent::Gap {
+                    expected: next,
+                    received: seq,
+                },
+                // without an instance id a smaller number can only mean the server started over
+                Some(_) => match server_id {
+                    Some(_) => SequenceEvent::Duplicate(seq),
+                    None => SequenceEvent::Restart(seq),
+                },
+            },
+        };
+
+        // never go backwards on a replayed message
+        match event {
+            SequenceEvent::Duplicate(_) => (),
+            _ => self.last_seq = Some(seq),
+        }
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_in_order_and_gaps() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.track(None, None), SequenceEvent::Untracked);
+        assert_eq!(tracker.track(Some(5), None), SequenceEvent::First(5));
+        assert_eq!(tracker.track(Some(6), None), SequenceEvent::InOrder(6));
+        let gap = tracker.track(Some(9), None);
+        assert_eq!(
+            gap,
+            SequenceEvent::Gap {
+                expected: 7,
+                received: 9
+            }
+        );
+        assert!(gap.needs_resend());
+        assert_eq!(tracker.track(Some(10), None), SequenceEvent::InOrder(10));
+        // a message without a number doesn't disturb the tracking
+        assert_eq!(tracker.track(None, None), SequenceEvent::Untracked);
+        assert_eq!(tracker.track(Some(11), None), SequenceEvent::InOrder(11));
+    }
+
+    #[test]
+    fn duplicates_dont_move_backwards() {
+        let mut tracker = SequenceTracker::new();
+        tracker.track(Some(10), Some("a"));
+        assert_eq!(
+            tracker.track(Some(10), Some("a")),
+            SequenceEvent::Duplicate(10)
+        );
+        // a replay of an older message from the same server
+        let replay = tracker.track(Some(7), Some("a"));
+        assert_eq!(replay, SequenceEvent::Duplicate(7));
+        assert!(!replay.needs_resend());
+        assert_eq!(
+            tracker.track(Some(11), Some("a")),
+            SequenceEvent::InOrder(11)
+        );
+    }
+
+    #[test]
+    fn server_restarts() {
+        let mut tracker = SequenceTracker::new();
+        tracker.track(Some(100), Some("a"));
+        let restart = tracker.track(Some(1), Some("b"));
+        assert_eq!(restart, SequenceEvent::Restart(1));
+        assert!(restart.needs_resend());
+        assert_eq!(tracker.track(Some(2), Some("b")), SequenceEvent::InOrder(2));
+        // without an id a counter that went back means the server started over
+        let mut tracker = SequenceTracker::new();
+        tracker.track(Some(100), None);
+        assert_eq!(tracker.track(Some(1), None), SequenceEvent::Restart(1));
+        assert_eq!(tracker.track(Some(2), None), SequenceEvent::InOrder(2));
+    }
+
+    #[test]
+    fn reconnects_start_over() {
+        let mut tracker = SequenceTracker::new();
+        tracker.track(Some(100), Some("a"));
+        tracker.reset();
+        assert_eq!(
+            tracker.track(Some(120), Some("a")),
+            SequenceEvent::First(120)
+        );
+        // the server id is kept, so a new instance is still noticed
+        tracker.reset();
+        assert_eq!(tracker.track(Some(1), Some("b")), SequenceEvent::Restart(1));
+    }
+
+    #[test]
+    fn counter_wraps_around() {
+        let mut tracker = SequenceTracker::new();
+        tracker.track(Some(u64::MAX - 1), Some("a"));
+        assert_eq!(
+            tracker.track(Some(u64::MAX), Some("a")),
+            SequenceEvent::InOrder(u64::MAX)
+        );
+        assert_eq!(tracker.track(Some(0), Some("a")), SequenceEvent::Restart(0));
+        assert_eq!(tracker.track(Some(1), Some("a")), SequenceEvent::InOrder(1));
+    }
+}
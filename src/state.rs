@@ -0,0 +1,254 @@
+use std::str::FromStr;
+
+// names of the senec system states, the index is the code from STAT_STATE
+#[rustfmt::skip]
+static STATE_NAMES: [&str; 98] = [
+    "INITIAL STATE", "ERROR INVERTER COMMUNICATION", "ERROR ELECTRICY METER", "RIPPLE CONTROL RECEIVER",
+    "INITIAL CHARGE", "MAINTENANCE CHARGE", "MAINTENANCE READY", "MAINTENANCE REQUIRED",
+    "MAN. SAFETY CHARGE", "SAFETY CHARGE READY", "FULL CHARGE", "EQUALIZATION: CHARGE",
+    "DESULFATATION: CHARGE", "BATTERY FULL", "CHARGE", "BATTERY EMPTY",
+    "DISCHARGE", "PV + DISCHARGE", "GRID + DISCHARGE", "PASSIVE",
+    "OFF", "OWN CONSUMPTION", "RESTART", "MAN. EQUALIZATION: CHARGE",
+    "MAN. DESULFATATION: CHARGE", "SAFE CHG", "BATTERY PROTECTION MODE", "EG ERROR",
+    "EG CHARGE", "EG DISCHARGE", "EG PASSIVE", "EG PROHIBIT CHARGE",
+    "EG PROHIBIT DISCHARGE", "EMERGANCY CHARGE", "SOFTWARE UPDATE", "NSP ERROR",
+    "NSP ERROR: GRID", "NSP ERROR: HARDWRE", "NO SERVER CONNECTION", "BMS ERROR",
+    "MAINTENANCE: FILTER", "SLEEPING MODE", "WAITING EXCESS", "CAPACITY TEST: CHARGE",
+    "CAPACITY TEST: DISCHARGE", "MAN. DESULFATATION: WAIT", "MAN. DESULFATATION: READY", "MAN. DESULFATATION: ERROR",
+    "EQUALIZATION: WAIT", "EMERGANCY CHARGE: ERROR", "MAN. EQUALIZATION: WAIT", "MAN. EQUALIZATION: ERROR",
+    "MAN: EQUALIZATION: READY", "AUTO. DESULFATATION: WAIT", "ABSORPTION PHASE", "DC-SWITCH OFF",
+    "PEAK-SHAVING: WAIT", "ERROR BATTERY INVERTER", "NPU-ERROR", "BMS OFFLINE",
+    "MAINTENANCE CHARGE ERROR", "MAN. SAFETY CHARGE ERROR", "SAFETY CHARGE ERROR", "NO CONNECTION TO MASTER",
+    "LITHIUM SAFE MODE ACTIVE", "LITHIUM SAFE MODE DONE", "BATTERY VOLTAGE ERROR", "BMS DC SWITCHED OFF",
+    "GRID INITIALIZATION", "GRID STABILIZATION", "REMOTE SHUTDOWN", "OFFPEAK-CHARGE",
+    "ERROR HALFBRIDGE", "BMS: ERROR OPERATING TEMPERATURE", "FACOTRY SETTINGS NOT FOUND", "BACKUP POWER MODE - ACTIVE",
+    "BACKUP POWER MODE - BATTERY EMPTY", "BACKUP POWER MODE ERROR", "INITIALISING", "INSTALLATION MODE",
+    "GRID OFFLINE", "BMS UPDATE NEEDED", "BMS CONFIGURATION NEEDED", "INSULATION TEST",
+    "SELFTEST", "EXTERNAL CONTROL", "ERROR: TEMPERATURESENSOR", "GRID OPERATOR: CHARGE PROHIBITED",
+    "GRID OPERATOR: DISCHARGE PROHIBITED", "SPARE CAPACITY", "SELFTEST ERROR", "EARTH FAULT",
+    "PV-MODE", "REMOTE DISCONNECTION", "ERROR DRM0", "BATTERY DIAGNOSIS",
+    "BALANCING", "SAFETY DISCHARGE",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    En,
+    De,
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" => Ok(Language::En),
+            "de" => Ok(Language::De),
+            _ => Err(anyhow::anyhow!("unsupported language {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Normal,
+    Maintenance,
+    Fault,
+}
+
+// the senec states grouped into what matters on the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemState {
+    Initializing,
+    Charge,
+    Discharge,
+    Full,
+    Empty,
+    Passive,
+    OwnConsumption,
+    SafetyCharge,
+    Maintenance,
+    Update,
+    GridControl,
+    BackupPower,
+    Sleep,
+    Off,
+    // the senec cloud is unreachable, the system itself keeps running
+    Offline,
+    Error,
+    Unknown,
+}
+
+impl SystemState {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0 | 22 | 68 | 69 | 78 => SystemState::Initializing,
+            // maintenance, equalization, desulfation and balancing charges run on their own
+            4 | 5 | 11 | 12 | 14 | 28 | 43 | 54 | 71 | 96 => SystemState::Charge,
+            16 | 17 | 18 | 29 | 44 => SystemState::Discharge,
+            10 | 13 => SystemState::Full,
+            15 => SystemState::Empty,
+            19 | 30 | 42 | 48 | 53 | 56 | 89 | 92 => SystemState::Passive,
+            21 => SystemState::OwnConsumption,
+            8 | 9 | 25 | 33 => SystemState::SafetyCharge,
+            6 | 7 | 23 | 24 | 26 | 40 | 45 | 46 | 50 | 52 | 64 | 65 | 79 | 81 | 82 | 83 | 84
+            | 95 | 97 => SystemState::Maintenance,
+            34 => SystemState::Update,
+            3 | 31 | 32 | 70 | 85 | 87 | 88 | 93 => SystemState::GridControl,
+            75 | 76 => SystemState::BackupPower,
+            41 => SystemState::Sleep,
+            20 | 55 | 67 => SystemState::Off,
+            38 => SystemState::Offline,
+            1 | 2 | 27 | 35 | 36 | 37 | 39 | 47 | 49 | 51 | 57 | 58 | 59 | 60 | 61 | 62 | 63
+            | 66 | 72 | 73 | 74 | 77 | 80 | 86 | 90 | 91 | 94 => SystemState::Error,
+            _ => SystemState::Unknown,
+        }
+    }
+    // stat_state is either the numeric code or the senec name of the state
+    pub fn parse(stat_state: &str) -> (Self, Option<u8>) {
+        let stat_state = stat_state.trim();
+        let code = match stat_state.parse::<u8>() {
+            Ok(code) => Some(code),
+            Err(_) => STATE_NAMES
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(stat_state))
+                .map(|code| code as u8),
+        };
+        match code {
+            Some(code) => (SystemState::from_code(code), Some(code)),
+            None => (SystemState::Unknown, None),
+        }
+    }
+    pub fn severity(&self) -> Severity {
+        match self {
+            SystemState::Error => Severity::Fault,
+            SystemState::Maintenance | SystemState::Off => Severity::Maintenance,
+            _ => Severity::Normal,
+        }
+    }
+    // at most 9 characters, that fits the status line in the smallest font on every panel
+    pub fn label(&self, language: Language) -> &'static str {
+        match language {
+            Language::En => match self {
+                SystemState::Initializing => "INIT",
+                SystemState::Charge => "CHARGE",
+                SystemState::Discharge => "DISCHARGE",
+                SystemState::Full => "FULL",
+                SystemState::Empty => "EMPTY",
+                SystemState::Passive => "PASSIVE",
+                SystemState::OwnConsumption => "OWN USE",
+                SystemState::SafetyCharge => "SAFE CHG",
+                SystemState::Maintenance => "SERVICE",
+                SystemState::Update => "UPDATE",
+                SystemState::GridControl => "GRID CTRL",
+                SystemState::BackupPower => "BACKUP",
+                SystemState::Sleep => "SLEEP",
+                SystemState::Off => "OFF",
+                SystemState::Offline => "OFFLINE",
+                SystemState::Error => "ERROR",
+                SystemState::Unknown => "UNKNOWN",
+            },
+            Language::De => match self {
+                SystemState::Initializing => "INIT",
+                SystemState::Charge => "LADEN",
+                SystemState::Discharge => "ENTLADEN",
+                SystemState::Full => "VOLL",
+                SystemState::Empty => "LEER",
+                SystemState::Passive => "PASSIV",
+                SystemState::OwnConsumption => "EIGENVERB",
+                SystemState::SafetyCharge => "SICH.LAD.",
+                SystemState::Maintenance => "WARTUNG",
+                SystemState::Update => "UPDATE",
+                SystemState::GridControl => "NETZBETR.",
+                SystemState::BackupPower => "NOTSTROM",
+                SystemState::Sleep => "RUHEMODUS",
+                SystemState::Off => "AUS",
+                SystemState::Offline => "OFFLINE",
+                SystemState::Error => "FEHLER",
+                SystemState::Unknown => "UNBEKANNT",
+            },
+        }
+    }
+}
+
+// full senec name for the log and the warning screen
+pub fn state_name(code: u8) -> Option<&'static str> {
+    STATE_NAMES.get(code as usize).copied()
+}
+
+// gui_charging_info and gui_boosting_info, they explain why the battery takes power from the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChargingInfo {
+    pub grid_charging: bool,
+    pub boosting: bool,
+}
+
+impl ChargingInfo {
+    pub fn parse(charging_info: &str, boosting_info: &str) -> Self {
+        ChargingInfo {
+            grid_charging: parse_flag("gui_charging_info", charging_info),
+            boosting: parse_flag("gui_boosting_info", boosting_info),
+        }
+    }
+    pub fn grid_charging_label(language: Language) -> &'static str {
+        match language {
+            Language::En => "GRID CHG",
+            Language::De => "NETZLAD.",
+        }
+    }
+    pub fn boosting_label(language: Language) -> &'static str {
+        match language {
+            Language::En => "BOOST",
+            Language::De => "BOOST",
+        }
+    }
+}
+
+// the flags come as "1", "true" or the raw senec value like "u8_01"
+fn parse_flag(field: &str, value: &str) -> bool {
+    let value = value.trim();
+    let value = value.strip_prefix("u8_").unwrap_or(value);
+    if let Ok(number) = value.parse::<u8>() {
+        return number != 0;
+    }
+    match value.to_lowercase().as_str() {
+        "true" => true,
+        "false" | "" => false,
+        _ => {
+            log::warn!("Unexpected value for {}: {}", field, value);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routine_charges_and_a_lost_server_dont_block_the_display() {
+        for code in [5, 11, 12, 48, 53, 96, 38] {
+            let state = SystemState::from_code(code);
+            assert_eq!(state.severity(), Severity::Normal, "{:?}", state_name(code));
+        }
+        assert_eq!(SystemState::from_code(96), SystemState::Charge);
+        assert_eq!(SystemState::from_code(38), SystemState::Offline);
+    }
+
+    #[test]
+    fn faults_and_service_states_still_warn() {
+        // bms error, earth fault, maintenance required, installation mode
+        assert_eq!(SystemState::from_code(39).severity(), Severity::Fault);
+        assert_eq!(SystemState::from_code(91).severity(), Severity::Fault);
+        assert_eq!(SystemState::from_code(7).severity(), Severity::Maintenance);
+        assert_eq!(SystemState::from_code(79).severity(), Severity::Maintenance);
+    }
+
+    #[test]
+    fn parses_codes_and_names() {
+        assert_eq!(SystemState::parse("38"), (SystemState::Offline, Some(38)));
+        assert_eq!(
+            SystemState::parse(" balancing "),
+            (SystemState::Charge, Some(96))
+        );
+        assert_eq!(SystemState::parse("whatever"), (SystemState::Unknown, None));
+    }
+}
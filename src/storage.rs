@@ -0,0 +1,425 @@
+use chrono::{Datelike, NaiveDate};
+
+use crate::history::{History, Sample};
+use crate::peaks::{Peak, Peaks, RECORDS};
+use crate::rollup::{Rollup, Series};
+
+// first byte of every blob, bumped whenever a layout changes so older blobs are ignored
+const FORMAT: u8 = 1;
+// timestamp and five 16 bit values
+const SAMPLE_SIZE: usize = 14;
+// two dates, four f32 and the complete flag
+const ROLLUP_SIZE: usize = 25;
+// value and timestamp for today and all time per record
+const PEAKS_SIZE: usize = RECORDS.len() * 2 * 8;
+
+// the flash handling, only on the esp32
+#[cfg(target_os = "espidf")]
+mod nvs {
+    use embedded_svc::storage::RawStorage;
+    use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::rollup::Rollups;
+
+    const NAMESPACE: &str = "senec";
+    const LAST_MESSAGE_KEY: &str = "last_msg";
+    const HISTORY_KEY: &str = "history";
+    const CO2_TOTAL_KEY: &str = "co2_total";
+    const PEAKS_KEY: &str = "peaks";
+    const ROLLUP_KEYS: [&str; 3] = ["roll_days", "roll_weeks", "roll_months"];
+    // same as the websocket frame buffer, larger messages can't be received anyway
+    const MAX_MESSAGE_LEN: usize = 2000;
+
+    // keeps the last message and the power history in nvs so they survive a reboot
+    pub struct Storage {
+        nvs: EspNvs<NvsDefault>,
+        // flash wears out, so writes are spaced at least this far apart
+        interval: Duration,
+        last_write: Option<Instant>,
+        last_history_timestamp: Option<u32>,
+    }
+
+    impl Storage {
+        pub fn new(partition: EspDefaultNvsPartition, interval: Duration) -> anyhow::Result<Self> {
+            let nvs = EspNvs::new(partition, NAMESPACE, true)?;
+            Ok(Storage {
+                nvs,
+                interval,
+                last_write: None,
+                last_history_timestamp: None,
+            })
+        }
+        pub fn load_last_message(&self) -> Option<String> {
+            let mut buf = vec![0; MAX_MESSAGE_LEN];
+            match self.nvs.get_raw(LAST_MESSAGE_KEY, &mut buf) {
+                Ok(Some(data)) => String::from_utf8(data.to_vec()).ok(),
+                Ok(None) => None,
+                Err(e) => {
+                    log::warn!("Error loading the last message: {:?}", e);
+                    None
+                }
+            }
+        }
+        // 0 on the first start
+        pub fn load_co2_total(&self) -> f32 {
+            let mut buf = [0; 4];
+            match self.nvs.get_raw(CO2_TOTAL_KEY, &mut buf) {
+                Ok(Some(data)) if data.len() == 4 => {
+                    f32::from_le_bytes([data[0], data[1], data[2], data[3]])
+                }
+                Ok(_) => 0.0,
+                Err(e) => {
+                    log::warn!("Error loading the CO2 total: {:?}", e);
+                    0.0
+                }
+            }
+        }
+        pub fn load_peaks(&self) -> Peaks {
+            let mut buf = [0; PEAKS_SIZE + 1];
+            match self.nvs.get_raw(PEAKS_KEY, &mut buf) {
+                Ok(Some(data)) => decode_peaks(data).unwrap_or_else(|| {
+                    log::warn!("Ignoring the stored peaks of {} bytes", data.len());
+                    Peaks::default()
+                }),
+                Ok(None) => Peaks::default(),
+                Err(e) => {
+                    log::warn!("Error loading the peaks: {:?}", e);
+                    Peaks::default()
+                }
+            }
+        }
+        pub fn load_rollups(&self, rollups: &mut Rollups) {
+            let [days, weeks, months] = ROLLUP_KEYS;
+            self.load_series(days, &mut rollups.days);
+            self.load_series(weeks, &mut rollups.weeks);
+            self.load_series(months, &mut rollups.months);
+        }
+        fn load_series<const N: usize>(&self, key: &str, series: &mut Series<N>) {
+            // the running period comes on top of the closed ones
+            let mut buf = vec![0; 1 + (N + 1) * ROLLUP_SIZE];
+            match self.nvs.get_raw(key, &mut buf) {
+                Ok(Some(data)) => match decode_series(data) {
+                    Some(rollups) => rollups
+                        .into_iter()
+                        .for_each(|rollup| series.restore(rollup)),
+                    None => log::warn!("Ignoring the stored rollups {}", key),
+                },
+                Ok(None) => (),
+                Err(e) => log::warn!("Error loading the rollups {}: {:?}", key, e),
+            }
+        }
+        // only changes once a day, so it is written right away
+        pub fn save_rollups(&mut self, rollups: &Rollups) -> anyhow::Result<()> {
+            let [days, weeks, months] = ROLLUP_KEYS;
+            self.nvs.set_raw(days, &encode_series(&rollups.days))?;
+            self.nvs.set_raw(weeks, &encode_series(&rollups.weeks))?;
+            self.nvs.set_raw(months, &encode_series(&rollups.months))?;
+            Ok(())
+        }
+        pub fn load_history<const N: usize>(&mut self, history: &mut History<N>) {
+            let mut buf = vec![0; 1 + N * SAMPLE_SIZE];
+            match self.nvs.get_raw(HISTORY_KEY, &mut buf) {
+                Ok(Some(data)) => match decode_history(data) {
+                    Some(samples) => {
+                        samples
+                            .into_iter()
+                            .for_each(|sample| history.insert(sample));
+                        self.last_history_timestamp =
+                            history.latest().map(|sample| sample.timestamp);
+                        log::info!("Restored {} history samples", history.len());
+                    }
+                    None => log::warn!("Ignoring the stored history of {} bytes", data.len()),
+                },
+                Ok(None) => (),
+                Err(e) => log::warn!("Error loading the history: {:?}", e),
+            }
+        }
+        // returns true when something was written
+        pub fn save_if_due<const N: usize>(
+            &mut self,
+            message: &str,
+            history: &History<N>,
+            co2_total: f32,
+            peaks: &Peaks,
+        ) -> anyhow::Result<bool> {
+            if let Some(last_write) = self.last_write {
+                if last_write.elapsed() < self.interval {
+                    return Ok(false);
+                }
+            }
+            if message.len() <= MAX_MESSAGE_LEN {
+                self.nvs.set_raw(LAST_MESSAGE_KEY, message.as_bytes())?;
+            }
+            // the history only changes once per resolution interval
+            let latest = history.latest().map(|sample| sample.timestamp);
+            if latest != self.last_history_timestamp {
+                self.nvs.set_raw(HISTORY_KEY, &encode_history(history))?;
+                self.last_history_timestamp = latest;
+            }
+            self.nvs.set_raw(CO2_TOTAL_KEY, &co2_total.to_le_bytes())?;
+            self.nvs.set_raw(PEAKS_KEY, &encode_peaks(peaks))?;
+            self.last_write = Some(Instant::now());
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(target_os = "espidf")]
+pub use nvs::Storage;
+
+// the records of a blob without the format byte, None for another format or a cut off blob
+fn records(data: &[u8], size: usize) -> Option<std::slice::ChunksExact<'_, u8>> {
+    match data.split_first() {
+        Some((&FORMAT, records)) if records.len() % size == 0 => Some(records.chunks_exact(size)),
+        _ => None,
+    }
+}
+
+// powers in 10W steps and the soc in 0.1% steps, that is plenty for the display
+pub fn encode_history<const N: usize>(history: &History<N>) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1 + history.len() * SAMPLE_SIZE);
+    buf.push(FORMAT);
+    for sample in history.iter() {
+        buf.extend_from_slice(&sample.timestamp.to_le_bytes());
+        for value in [sample.house, sample.pv, sample.battery, sample.grid] {
+            buf.extend_from_slice(&((value * 100.0).round() as i16).to_le_bytes());
+        }
+        buf.extend_from_slice(&((sample.soc * 10.0).round() as i16).to_le_bytes());
+    }
+    buf
+}
+
+pub fn decode_history(data: &[u8]) -> Option<Vec<Sample>> {
+    let samples = records(data, SAMPLE_SIZE)?.map(|data| {
+        let value = |idx: usize| i16::from_le_bytes([data[4 + idx * 2], data[5 + idx * 2]]) as f32;
+        Sample {
+            timestamp: u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+            house: value(0) / 100.0,
+            pv: value(1) / 100.0,
+            battery: value(2) / 100.0,
+            grid: value(3) / 100.0,
+            soc: value(4) / 10.0,
+        }
+    });
+    Some(samples.collect())
+}
+
+pub fn encode_series<const N: usize>(series: &Series<N>) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1 + series.len() * ROLLUP_SIZE);
+    buf.push(FORMAT);
+    for rollup in series.iter() {
+        buf.extend_from_slice(&rollup.start.num_days_from_ce().to_le_bytes());
+        buf.extend_from_slice(&rollup.end.num_days_from_ce().to_le_bytes());
+        for value in [
+            rollup.consumption,
+            rollup.generation,
+            rollup.grid_import,
+            rollup.grid_export,
+        ] {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        buf.push(rollup.complete as u8);
+    }
+    buf
+}
+
+// oldest first, like they are restored
+pub fn decode_series(data: &[u8]) -> Option<Vec<Rollup>> {
+    records(data, ROLLUP_SIZE)?.map(decode_rollup).collect()
+}
+
+fn decode_rollup(data: &[u8]) -> Option<Rollup> {
+    let word = |idx: usize| [data[idx], data[idx + 1], data[idx + 2], data[idx + 3]];
+    let date = |idx: usize| NaiveDate::from_num_days_from_ce_opt(i32::from_le_bytes(word(idx)));
+    let (start, end) = (date(0)?, date(4)?);
+    if end < start || data[24] > 1 {
+        return None;
+    }
+    Some(Rollup {
+        start,
+        end,
+        consumption: f32::from_le_bytes(word(8)),
+        generation: f32::from_le_bytes(word(12)),
+        grid_import: f32::from_le_bytes(word(16)),
+        grid_export: f32::from_le_bytes(word(20)),
+        complete: data[24] == 1,
+    })
+}
+
+// a timestamp of 0 marks a missing peak, they are only recorded with a synced clock
+pub fn encode_peaks(peaks: &Peaks) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1 + PEAKS_SIZE);
+    buf.push(FORMAT);
+    for set in [&peaks.today, &peaks.all_time] {
+        for record in RECORDS {
+            let peak = set.get(record).unwrap_or(Peak {
+                value: 0.0,
+                timestamp: 0,
+            });
+            buf.extend_from_slice(&peak.value.to_le_bytes());
+            buf.extend_from_slice(&peak.timestamp.to_le_bytes());
+        }
+    }
+    buf
+}
+
+pub fn decode_peaks(data: &[u8]) -> Option<Peaks> {
+    if data.len() != 1 + PEAKS_SIZE {
+        return None;
+    }
+    let mut peaks = Peaks::default();
+    for (idx, chunk) in records(data, 8)?.enumerate() {
+        let peak = Peak {
+            value: f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+            timestamp: u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+        };
+        let set = match idx < RECORDS.len() {
+            true => &mut peaks.today,
+            false => &mut peaks.all_time,
+        };
+        if peak.timestamp != 0 {
+            set.set(RECORDS[idx % RECORDS.len()], Some(peak));
+        }
+    }
+    Some(peaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::energy::{DayEnergy, EnergyTotals};
+    use crate::rollup::Rollups;
+    use crate::types::PowerValues;
+
+    fn history() -> History<8> {
+        let mut history = History::new(60);
+        for (idx, timestamp) in [1_718_971_200, 1_718_971_260, 1_718_971_320]
+            .into_iter()
+            .enumerate()
+        {
+            let power = PowerValues {
+                house: 1.23,
+                pv: 4.56 + idx as f32,
+                battery: -0.78,
+                grid: -2.5,
+            };
+            history.push(timestamp, &power, 55.5);
+        }
+        history.flush();
+        history
+    }
+
+    #[test]
+    fn history_round_trip() {
+        let history = history();
+        let decoded = decode_history(&encode_history(&history)).unwrap();
+        assert_eq!(decoded.len(), 3);
+        for (sample, decoded) in history.iter().zip(decoded.iter()) {
+            assert_eq!(decoded.timestamp, sample.timestamp);
+            // stored in 10W and 0.1% steps
+            for (value, decoded) in [
+                (sample.house, decoded.house),
+                (sample.pv, decoded.pv),
+                (sample.battery, decoded.battery),
+                (sample.grid, decoded.grid),
+                (sample.soc, decoded.soc),
+            ] {
+                assert!((value - decoded).abs() <= 0.005, "{} {}", value, decoded);
+            }
+        }
+        assert_eq!(
+            decode_history(&encode_history(&History::<8>::new(60))),
+            Some(Vec::new())
+        );
+    }
+
+    #[test]
+    fn rollups_round_trip() {
+        let mut rollups = Rollups::new();
+        let mut date = NaiveDate::from_ymd_opt(2024, 3, 30).unwrap();
+        for idx in 0..4 {
+            rollups.add_day(&DayEnergy {
+                date,
+                energy: EnergyTotals {
+                    consumption: 10.5 + idx as f32,
+                    generation: 20.25,
+                    grid_import: 3.0,
+                    grid_export: 12.75,
+                    ..EnergyTotals::default()
+                },
+                complete: idx > 0,
+            });
+            date = date.succ_opt().unwrap();
+        }
+        let decoded = decode_series(&encode_series(&rollups.months)).unwrap();
+        assert_eq!(decoded, rollups.months.iter().copied().collect::<Vec<_>>());
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(
+            decode_series(&encode_series(&rollups.days)).unwrap().len(),
+            4
+        );
+    }
+
+    #[test]
+    fn peaks_round_trip() {
+        let mut peaks = Peaks::default();
+        let power = PowerValues {
+            house: 1.5,
+            pv: 6.25,
+            battery: 0.0,
+            grid: -3.0,
+        };
+        peaks.update(&power, Some(42.0), 1_718_971_200);
+        // a missing peak stays missing
+        peaks.today.set(RECORDS[0], None);
+        assert_eq!(decode_peaks(&encode_peaks(&peaks)), Some(peaks));
+        assert_eq!(
+            decode_peaks(&encode_peaks(&Peaks::default())),
+            Some(Peaks::default())
+        );
+    }
+
+    #[test]
+    fn truncated_blobs_are_rejected() {
+        let history = encode_history(&history());
+        assert_eq!(decode_history(&history[..history.len() - 1]), None);
+        assert_eq!(decode_history(&[]), None);
+        let peaks = encode_peaks(&Peaks::default());
+        assert_eq!(decode_peaks(&peaks[..peaks.len() - 8]), None);
+        let mut rollups = Rollups::new();
+        rollups.add_day(&DayEnergy {
+            date: NaiveDate::from_ymd_opt(2024, 3, 30).unwrap(),
+            energy: EnergyTotals::default(),
+            complete: true,
+        });
+        let series = encode_series(&rollups.days);
+        assert_eq!(decode_series(&series[..series.len() - 1]), None);
+    }
+
+    #[test]
+    fn blobs_of_another_format_are_rejected() {
+        // written before the format byte, the first byte is part of a timestamp
+        let mut history = encode_history(&history());
+        history.remove(0);
+        assert_eq!(decode_history(&history), None);
+        let mut peaks = encode_peaks(&Peaks::default());
+        peaks[0] = FORMAT + 1;
+        assert_eq!(decode_peaks(&peaks), None);
+        // a rollup ending before it starts or a broken flag
+        let mut rollups = Rollups::new();
+        rollups.add_day(&DayEnergy {
+            date: NaiveDate::from_ymd_opt(2024, 3, 30).unwrap(),
+            energy: EnergyTotals::default(),
+            complete: true,
+        });
+        let series = encode_series(&rollups.days);
+        let mut flag = series.clone();
+        flag[25] = 7;
+        assert_eq!(decode_series(&flag), None);
+        let mut dates = series;
+        dates[5..9].copy_from_slice(&0i32.to_le_bytes());
+        assert_eq!(decode_series(&dates), None);
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::anyhow;
+
+use crate::clock::{self, TimeWindow};
+use crate::energy::EnergyTotals;
+use crate::state::Language;
+
+// a time of use window with its own import price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouBand {
+    pub window: TimeWindow,
+    pub price: f32,
+}
+
+// prices per kWh in the configured currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tariff {
+    pub import_price: f32,
+    pub feed_in: f32,
+    pub bands: Vec<TouBand>,
+}
+
+impl Tariff {
+    // the first matching band wins, outside of all bands the normal import price applies
+    pub fn import_price_at(&self, minute: Option<u16>) -> f32 {
+        minute
+            .and_then(|minute| self.bands.iter().find(|band| band.window.contains(minute)))
+            .map_or(self.import_price, |band| band.price)
+    }
+}
+
+// "22:00-06:00=0.21;12:00-14:00=0.15", overlapping bands are kept in their order so the first
+// one wins. A band that starts where it ends would never apply, so it is an error
+pub fn parse_bands(bands: &str) -> anyhow::Result<Vec<TouBand>> {
+    bands
+        .split(';')
+        .map(str::trim)
+        .filter(|band| !band.is_empty())
+        .map(|band| {
+            let (window, price) = band
+                .split_once('=')
+                .ok_or(anyhow!("missing price in band {}", band))?;
+            let window = clock::parse_window(window)?;
+            if window.start == window.end {
+                return Err(anyhow!("empty window in band {}", band));
+            }
+            Ok(TouBand {
+                window,
+                price: price.trim().parse()?,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Costs {
+    // paid for grid import
+    pub cost: f32,
+    // received for the feed in
+    pub earnings: f32,
+    // grid import avoided by using own power
+    pub savings: f32,
+}
+
+impl Costs {
+    // energy is the delta since the last message, priced at the current band
+    pub fn add(&mut self, energy: &EnergyTotals, tariff: &Tariff, minute: Option<u16>) {
+        let price = tariff.import_price_at(minute);
+        self.cost += energy.grid_import * price;
+        self.earnings += energy.grid_export * tariff.feed_in;
+        self.savings += energy.self_supplied() * price;
+    }
+}
+
+// at most 5 characters including the sign so it fits left of the totals icons, the precision
+// is picked after rounding so 9.996 doesn't become "+10.00"
+pub fn format_money(amount: f32, sign: &str) -> String {
+    let amount = amount.abs().min(9999.0);
+    let digits = (0..=2)
+        .rev()
+        .map(|precision| format!("{:.*}", precision, amount))
+        .find(|digits| digits.len() <= 4)
+        .unwrap_or_else(|| format!("{:.0}", amount));
+    format!("{}{}", sign, digits)
+}
+
+// header of the savings in the totals area
+pub fn savings_label(language: Language) -> &'static str {
+    match language {
+        Language::En => "saved",
+        Language::De => "gespart",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tariff(bands: &str) -> Tariff {
+        Tariff {
+            import_price: 0.32,
+            feed_in: 0.08,
+            bands: parse_bands(bands).unwrap(),
+        }
+    }
+
+    #[test]
+    fn bands_wrap_past_midnight() {
+        let tariff = tariff("22:00-06:00=0.24");
+        assert_eq!(
+            tariff.bands,
+            vec![TouBand {
+                window: TimeWindow {
+                    start: 22 * 60,
+                    end: 6 * 60,
+                },
+                price: 0.24,
+            }]
+        );
+        assert_eq!(tariff.import_price_at(Some(22 * 60)), 0.24);
+        assert_eq!(tariff.import_price_at(Some(0)), 0.24);
+        assert_eq!(tariff.import_price_at(Some(6 * 60 - 1)), 0.24);
+        assert_eq!(tariff.import_price_at(Some(6 * 60)), 0.32);
+        assert_eq!(tariff.import_price_at(Some(12 * 60)), 0.32);
+        // without a clock the band is unknown
+        assert_eq!(tariff.import_price_at(None), 0.32);
+    }
+
+    #[test]
+    fn the_first_overlapping_band_wins() {
+        let tariff = tariff(" 12:00-14:00=0.28 ; 13:00-15:00=0.15;");
+        assert_eq!(tariff.bands.len(), 2);
+        assert_eq!(tariff.import_price_at(Some(13 * 60)), 0.28);
+        assert_eq!(tariff.import_price_at(Some(14 * 60)), 0.15);
+        assert_eq!(tariff.import_price_at(Some(15 * 60)), 0.32);
+        assert_eq!(tariff.import_price_at(Some(11 * 60)), 0.32);
+    }
+
+    #[test]
+    fn malformed_bands_are_rejected() {
+        assert_eq!(parse_bands("").unwrap(), Vec::new());
+        assert_eq!(parse_bands("00:00-24:00=0.2").unwrap().len(), 1);
+        for bands in [
+            "22:00-06:00",
+            "22:00=0.24",
+            "22:00-06:00=cheap",
+            "25:00-06:00=0.24",
+            "22:60-06:00=0.24",
+            "22-06=0.24",
+            "06:00-06:00=0.24",
+            "12:00-14:00=0.28;oops",
+        ] {
+            assert!(parse_bands(bands).is_err(), "{}", bands);
+        }
+    }
+
+    #[test]
+    fn costs_use_the_price_of_the_band() {
+        let tariff = tariff("22:00-06:00=0.24");
+        let mut costs = Costs::default();
+        let energy = EnergyTotals {
+            consumption: 3.0,
+            grid_import: 1.0,
+            grid_export: 2.0,
+            ..EnergyTotals::default()
+        };
+        costs.add(&energy, &tariff, Some(23 * 60));
+        assert!((costs.cost - 0.24).abs() < 1e-6);
+        assert!((costs.earnings - 0.16).abs() < 1e-6);
+        assert!((costs.savings - 0.48).abs() < 1e-6);
+    }
+
+    #[test]
+    fn money_fits_in_five_characters() {
+        assert_eq!(format_money(1.234, "+"), "+1.23");
+        assert_eq!(format_money(-1.234, "-"), "-1.23");
+        assert_eq!(format_money(9.996, "+"), "+10.0");
+        assert_eq!(format_money(99.96, ""), "100");
+        assert_eq!(format_money(1234.4, "-"), "-1234");
+        assert_eq!(format_money(123456.0, "+"), "+9999");
+        assert_eq!(format_money(0.0, "+"), "+0.00");
+    }
+}
@@ -13,6 +13,11 @@ pub struct NewUiStruct<'a> {
     pub gui_boosting_info: &'a str,
     pub weather: WeatherNew,
     pub total_data: TotalDataNew<'a>,
+    // optional envelope fields, older servers don't send them
+    #[serde(default)]
+    pub seq: Option<u64>,
+    #[serde(default, borrow)]
+    pub server_id: Option<&'a str>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,4 +50,51 @@ pub struct HourlyNew {
     pub cloud_cover: Vec<String>,
     pub uv_index: Vec<String>,
     pub uv_index_clear_sky: Vec<String>,
+    // wmo weather codes, older servers don't send them
+    #[serde(default, alias = "weathercode")]
+    pub weather_code: Vec<String>,
+}
+
+// the power values of a message as numbers, all in kW
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PowerValues {
+    pub house: f32,
+    pub pv: f32,
+    // positive while charging, negative while discharging
+    pub battery: f32,
+    // positive while drawing from the grid, negative while feeding in
+    pub grid: f32,
+}
+
+impl PowerValues {
+    pub fn parse(values: &NewUiStruct) -> Option<Self> {
+        Some(PowerValues {
+            house: parse_number(values.gui_house_pow)?,
+            pv: parse_number(values.gui_inverter_power)?,
+            battery: parse_number(values.gui_bat_data_power)?,
+            grid: parse_number(values.gui_grid_pow)?,
+        })
+    }
+    pub fn grid_import(&self) -> f32 {
+        self.grid.max(0.0)
+    }
+    pub fn grid_export(&self) -> f32 {
+        (-self.grid).max(0.0)
+    }
+    pub fn battery_charge(&self) -> f32 {
+        self.battery.max(0.0)
+    }
+    pub fn battery_discharge(&self) -> f32 {
+        (-self.battery).max(0.0)
+    }
+}
+
+// the server sends numbers as strings like "1.23", "-0.00" or "85"
+pub fn parse_number(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .trim_end_matches('%')
+        .trim()
+        .parse::<f32>()
+        .ok()
 }
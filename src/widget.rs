@@ -0,0 +1,830 @@
+use core::convert::Infallible;
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::image::{Image, ImageRaw};
+use embedded_graphics::mono_font::ascii::{
+    FONT_10X20, FONT_4X6, FONT_5X8, FONT_6X10, FONT_6X12, FONT_7X13, FONT_9X15,
+};
+use embedded_graphics::mono_font::{MonoFont, MonoTextStyleBuilder};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Point, Size};
+use embedded_graphics::primitives::{
+    Circle, Line, PointsIter, Primitive, PrimitiveStyleBuilder, Rectangle,
+};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use embedded_graphics::{Drawable, Pixel};
+
+// the ascii fonts have no ellipsis character
+pub const ELLIPSIS: &str = "..";
+// the dashboard coordinates are given for the 2.9" panel
+pub const DESIGN_SIZE: Size = Size::new(296, 128);
+// from small to large, the scaled fonts are picked from these
+const FONTS: [&MonoFont<'static>; 7] = [
+    &FONT_4X6,
+    &FONT_5X8,
+    &FONT_6X10,
+    &FONT_6X12,
+    &FONT_7X13,
+    &FONT_9X15,
+    &FONT_10X20,
+];
+
+// maps the design coordinates to the resolution of the panel. Both axes use the same scale so
+// circles stay round, the rest of the panel is left blank around the dashboard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    scale: f32,
+    offset: Point,
+}
+
+impl Layout {
+    pub fn new(size: Size) -> Self {
+        let scale = (size.width as f32 / DESIGN_SIZE.width as f32)
+            .min(size.height as f32 / DESIGN_SIZE.height as f32);
+        let used = Size::new(
+            (DESIGN_SIZE.width as f32 * scale) as u32,
+            (DESIGN_SIZE.height as f32 * scale) as u32,
+        );
+        Layout {
+            scale,
+            offset: Point::new(
+                (size.width.saturating_sub(used.width) / 2) as i32,
+                (size.height.saturating_sub(used.height) / 2) as i32,
+            ),
+        }
+    }
+    pub fn point(&self, x: i32, y: i32) -> Point {
+        self.offset
+            + Point::new(
+                (x as f32 * self.scale) as i32,
+                (y as f32 * self.scale) as i32,
+            )
+    }
+    // the corners are scaled, so neighbouring rectangles still line up
+    pub fn rect(&self, x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        let top_left = self.point(x, y);
+        let bottom_right = self.point(x + width as i32, y + height as i32);
+        let size = bottom_right - top_left;
+        Rectangle::new(top_left, Size::new(size.x as u32, size.y as u32))
+    }
+    // diameters and line widths
+    pub fn length(&self, length: u32) -> u32 {
+        ((length as f32 * self.scale).round() as u32).max(1)
+    }
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+    // the largest font that is not higher than the scaled font
+    pub fn font(&self, font: &'static MonoFont<'static>) -> &'static MonoFont<'static> {
+        let height = font.character_size.height as f32 * self.scale;
+        FONTS
+            .iter()
+            .rev()
+            .find(|candidate| candidate.character_size.height as f32 <= height)
+            .copied()
+            .unwrap_or(FONTS[0])
+    }
+    // icons are only enlarged by whole pixels, they would be unrecognizable otherwise. On panels
+    // smaller than the design they shrink with the layout so they stay inside the circles
+    pub fn icon_scale(&self) -> f32 {
+        match self.scale < 1.0 {
+            true => self.scale,
+            false => self.scale.floor(),
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::new(DESIGN_SIZE)
+    }
+}
+
+// the layer a widget is drawn into, highlights are red on three color panels and black otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRole {
+    Foreground,
+    Highlight,
+}
+
+// something on the screen that owns a region, it only ever draws into and clears that region
+pub trait Widget {
+    fn bounds(&self) -> Rectangle;
+    fn role(&self) -> ColorRole {
+        ColorRole::Foreground
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>;
+    fn clear<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        target.fill_solid(&self.bounds(), BinaryColor::Off)?;
+        Ok(())
+    }
+    fn redraw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        self.clear(target)?;
+        self.draw(target)
+    }
+}
+
+// one line of text, vertically centered in its region
+pub struct Label {
+    bounds: Rectangle,
+    font: &'static MonoFont<'static>,
+    // used when the text does not fit with the font
+    small_font: Option<&'static MonoFont<'static>>,
+    alignment: Alignment,
+    inverted: bool,
+    role: ColorRole,
+    text: String,
+}
+
+impl Label {
+    pub fn new(bounds: Rectangle, font: &'static MonoFont<'static>) -> Self {
+        Label {
+            bounds,
+            font,
+            small_font: None,
+            alignment: Alignment::Left,
+            inverted: false,
+            role: ColorRole::Foreground,
+            text: String::new(),
+        }
+    }
+    pub fn aligned(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+    pub fn shrink_to(mut self, font: &'static MonoFont<'static>) -> Self {
+        self.small_font = Some(font);
+        self
+    }
+    // light text on a dark background
+    pub fn inverted(mut self) -> Self {
+        self.inverted = true;
+        self
+    }
+    pub fn highlighted(mut self) -> Self {
+        self.role = ColorRole::Highlight;
+        self
+    }
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+        self
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn set_text(&mut self, text: &str) {
+        self.text.clear();
+        self.text.push_str(text);
+    }
+    pub fn set_role(&mut self, role: ColorRole) {
+        self.role = role;
+    }
+    // the font and text that are actually drawn
+    pub fn fitted(&self) -> (&'static MonoFont<'static>, String) {
+        let width = available_width(&self.bounds, self.alignment);
+        match self.small_font {
+            Some(small) => fit(&self.text, width, &[self.font, small]),
+            None => fit(&self.text, width, &[self.font]),
+        }
+    }
+}
+
+impl Widget for Label {
+    fn bounds(&self) -> Rectangle {
+        self.bounds
+    }
+    fn role(&self) -> ColorRole {
+        self.role
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        let (background, color) = match self.inverted {
+            true => (BinaryColor::On, BinaryColor::Off),
+            false => (BinaryColor::Off, BinaryColor::On),
+        };
+        if self.inverted {
+            target.fill_solid(&self.bounds, background)?;
+        }
+        let (font, text) = self.fitted();
+        let x = left_x(&self.bounds, text_width(&text, font), self.alignment);
+        Text::with_text_style(
+            &text,
+            Point::new(x, self.bounds.center().y),
+            MonoTextStyleBuilder::new()
+                .font(font)
+                .text_color(color)
+                .build(),
+            TextStyleBuilder::new()
+                .alignment(Alignment::Left)
+                .baseline(Baseline::Middle)
+                .build(),
+        )
+        .draw(target)?;
+        Ok(())
+    }
+}
+
+// a packed 1 bit image as build.rs writes it, every row starts on a new byte and the
+// highest bit is the leftmost pixel, 1 is a set pixel
+pub struct Bitmap {
+    width: u32,
+    data: &'static [u8],
+}
+
+impl Bitmap {
+    pub const fn new(width: u32, data: &'static [u8]) -> Self {
+        Bitmap { width, data }
+    }
+    pub fn image(&self) -> ImageRaw<'static, BinaryColor> {
+        ImageRaw::new(self.data, self.width)
+    }
+    pub fn size(&self) -> Size {
+        let row = self.width.div_ceil(8) as usize;
+        Size::new(self.width, (self.data.len() / row) as u32)
+    }
+    fn color(&self, x: u32, y: u32) -> BinaryColor {
+        let row = self.width.div_ceil(8);
+        match self.data[(y * row + x / 8) as usize] & (0x80 >> (x % 8)) {
+            0 => BinaryColor::Off,
+            _ => BinaryColor::On,
+        }
+    }
+}
+
+pub struct Icon {
+    top_left: Point,
+    bitmap: &'static Bitmap,
+    // size of a bitmap pixel, scaled icons pick the nearest bitmap pixel for every screen pixel
+    scale: f32,
+}
+
+impl Icon {
+    pub fn new(top_left: Point, bitmap: &'static Bitmap) -> Self {
+        Icon {
+            top_left,
+            bitmap,
+            scale: 1.0,
+        }
+    }
+    pub fn scaled(mut self, scale: f32) -> Self {
+        self.scale = scale.max(0.1);
+        self
+    }
+    pub fn size(&self) -> Size {
+        let size = self.bitmap.size();
+        let scaled = |length: u32| ((length as f32 * self.scale).round() as u32).max(1);
+        Size::new(scaled(size.width), scaled(size.height))
+    }
+    pub fn set_bitmap(&mut self, bitmap: &'static Bitmap) {
+        self.bitmap = bitmap;
+    }
+}
+
+impl Widget for Icon {
+    fn bounds(&self) -> Rectangle {
+        Rectangle::new(self.top_left, self.size())
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        // unscaled the rows are copied as they are
+        if self.scale == 1.0 {
+            Image::new(&self.bitmap.image(), self.top_left).draw(target)?;
+            return Ok(());
+        }
+        let (source, size) = (self.bitmap.size(), self.size());
+        let pixels = (0..size.height).flat_map(|y| {
+            (0..size.width).map(move |x| {
+                let color = self.bitmap.color(
+                    x * source.width / size.width,
+                    y * source.height / size.height,
+                );
+                Pixel(self.top_left + Point::new(x as i32, y as i32), color)
+            })
+        });
+        target.draw_iter(pixels)?;
+        Ok(())
+    }
+}
+
+// a circle outline with its icon sitting on top of the outline
+pub struct CircleFrame {
+    top_left: Point,
+    diameter: u32,
+    stroke: u32,
+    icon: Icon,
+}
+
+impl CircleFrame {
+    // the icon is centered horizontally at the given height
+    pub fn new(top_left: Point, diameter: u32, icon_y: i32, bitmap: &'static Bitmap) -> Self {
+        CircleFrame {
+            top_left,
+            diameter,
+            stroke: 2,
+            icon: Icon::new(Point::new(top_left.x, icon_y), bitmap),
+        }
+        .icon_scaled(1.0)
+    }
+    pub fn stroke(mut self, stroke: u32) -> Self {
+        self.stroke = stroke;
+        self
+    }
+    pub fn icon_scaled(mut self, scale: f32) -> Self {
+        self.icon = self.icon.scaled(scale);
+        let width = self.icon.size().width as i32;
+        self.icon.top_left.x = self.top_left.x + (self.diameter as i32 - width) / 2;
+        self
+    }
+}
+
+impl Widget for CircleFrame {
+    fn bounds(&self) -> Rectangle {
+        envelope(
+            &Rectangle::new(self.top_left, Size::new(self.diameter, self.diameter)),
+            &self.icon.bounds(),
+        )
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        Circle::new(self.top_left, self.diameter)
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .stroke_color(BinaryColor::On)
+                    .stroke_width(self.stroke)
+                    .build(),
+            )
+            .draw(target)?;
+        // the icon interrupts the outline
+        target.fill_solid(&self.icon.bounds(), BinaryColor::Off)?;
+        self.icon.draw(target)
+    }
+}
+
+// a straight line, used for the separators
+pub struct Separator {
+    start: Point,
+    end: Point,
+    width: u32,
+    role: ColorRole,
+}
+
+impl Separator {
+    pub fn new(start: Point, end: Point, width: u32) -> Self {
+        Separator {
+            start,
+            end,
+            width,
+            role: ColorRole::Foreground,
+        }
+    }
+    pub fn highlighted(mut self) -> Self {
+        self.role = ColorRole::Highlight;
+        self
+    }
+}
+
+impl Widget for Separator {
+    fn bounds(&self) -> Rectangle {
+        Rectangle::with_corners(self.start, self.end)
+    }
+    fn role(&self) -> ColorRole {
+        self.role
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        Line::new(self.start, self.end)
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .stroke_color(BinaryColor::On)
+                    .stroke_width(self.width)
+                    .build(),
+            )
+            .draw(target)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stroke {
+    Solid,
+    // every other pixel, tells the lines apart without color
+    Dotted,
+}
+
+// one line of a sparkline, named in the legend
+pub struct Series {
+    name: &'static str,
+    stroke: Stroke,
+    values: Vec<f32>,
+}
+
+// line chart of a few series on one y axis that is scaled to fit them and always includes zero.
+// the header has the title with the top of the axis on the left and the legend on the right
+pub struct Sparkline {
+    bounds: Rectangle,
+    font: &'static MonoFont<'static>,
+    unit: &'static str,
+    // size of a plotted pixel, the lines get thicker on the larger panels
+    pixel: u32,
+    // space to the edges of the bounds, which are cleared as a whole
+    padding: u32,
+    title: String,
+    series: Vec<Series>,
+}
+
+impl Sparkline {
+    pub fn new(bounds: Rectangle, font: &'static MonoFont<'static>, unit: &'static str) -> Self {
+        Sparkline {
+            bounds,
+            font,
+            unit,
+            pixel: 1,
+            padding: 0,
+            title: String::new(),
+            series: Vec::new(),
+        }
+    }
+    pub fn with_series(mut self, name: &'static str, stroke: Stroke) -> Self {
+        self.series.push(Series {
+            name,
+            stroke,
+            values: Vec::new(),
+        });
+        self
+    }
+    pub fn pixel(mut self, pixel: u32) -> Self {
+        self.pixel = pixel.max(1);
+        self
+    }
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+    pub fn set_title(&mut self, title: &str) {
+        self.title.clear();
+        self.title.push_str(title);
+    }
+    // oldest first, spread over the whole width
+    pub fn set_values(&mut self, series: usize, values: impl IntoIterator<Item = f32>) {
+        if let Some(series) = self.series.get_mut(series) {
+            series.values.clear();
+            series
+                .values
+                .extend(values.into_iter().filter(|value| value.is_finite()));
+        }
+    }
+    // bottom and top of the y axis
+    pub fn range(&self) -> (f32, f32) {
+        let (min, max) = self
+            .series
+            .iter()
+            .flat_map(|series| series.values.iter())
+            .fold((0.0_f32, 0.0_f32), |(min, max), value| {
+                (min.min(*value), max.max(*value))
+            });
+        // a flat line at zero still needs an axis
+        match max > min {
+            true => (min, max),
+            false => (min, min + 1.0),
+        }
+    }
+    fn content(&self) -> Rectangle {
+        self.bounds.offset(-(self.padding as i32))
+    }
+    // the area below the header
+    pub fn plot(&self) -> Rectangle {
+        let content = self.content();
+        let header = self.font.character_size.height + 1;
+        Rectangle::new(
+            content.top_left + Point::new(0, header as i32),
+            Size::new(
+                content.size.width,
+                content.size.height.saturating_sub(header),
+            ),
+        )
+    }
+    fn y(&self, value: f32, (min, max): (f32, f32), plot: &Rectangle) -> i32 {
+        let height = plot.size.height.saturating_sub(self.pixel) as f32;
+        plot.top_left.y + ((max - value) / (max - min) * height).round() as i32
+    }
+    fn line<'a>(&'a self, points: &'a [Point], stroke: Stroke) -> impl Iterator<Item = Point> + 'a {
+        let pixel = self.pixel as i32;
+        points
+            .windows(2)
+            .flat_map(|pair| Line::new(pair[0], pair[1]).points())
+            // the dots are spaced by the pixel size so they stay apart on the larger panels
+            .enumerate()
+            .filter(move |(idx, _)| stroke == Stroke::Solid || (*idx as i32 / pixel) % 2 == 0)
+            .map(|(_, point)| point)
+    }
+    fn draw_points<D>(
+        &self,
+        target: &mut D,
+        points: impl Iterator<Item = Point>,
+    ) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        let size = Size::new(self.pixel, self.pixel);
+        for point in points {
+            target.fill_solid(&Rectangle::new(point, size), BinaryColor::On)?;
+        }
+        Ok(())
+    }
+}
+
+impl Widget for Sparkline {
+    fn bounds(&self) -> Rectangle {
+        self.bounds
+    }
+    fn draw<D>(&self, target: &mut D) -> anyhow::Result<()>
+    where
+        D: DrawTarget<Color = BinaryColor, Error = Infallible>,
+    {
+        let range = self.range();
+        let content = self.content();
+        let style = MonoTextStyleBuilder::new()
+            .font(self.font)
+            .text_color(BinaryColor::On)
+            .build();
+        let top = TextStyleBuilder::new()
+            .alignment(Alignment::Left)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(
+            &format!("{} {:.1}{}", self.title, range.1, self.unit),
+            content.top_left,
+            style,
+            top,
+        )
+        .draw(target)?;
+        // the legend from the right edge, a short sample of the line before each name
+        let sample = 3 * self.font.character_size.width as i32;
+        let middle = content.top_left.y + self.font.character_size.height as i32 / 2;
+        let mut x = content.top_left.x + content.size.width as i32;
+        for series in self.series.iter().rev() {
+            x -= text_width(series.name, self.font) as i32;
+            Text::with_text_style(series.name, Point::new(x, content.top_left.y), style, top)
+                .draw(target)?;
+            x -= sample + self.font.character_spacing as i32 + 2;
+            let points = [Point::new(x, middle), Point::new(x + sample - 1, middle)];
+            self.draw_points(target, self.line(&points, series.stroke))?;
+            x -= self.font.character_size.width as i32;
+        }
+
+        let plot = self.plot();
+        let right = plot.top_left.x + plot.size.width.saturating_sub(self.pixel) as i32;
+        // the zero line, sparser than the dotted series
+        let zero = self.y(0.0, range, &plot);
+        let step = 4 * self.pixel as usize;
+        let dots = (plot.top_left.x..=right)
+            .step_by(step)
+            .map(|x| Point::new(x, zero));
+        self.draw_points(target, dots)?;
+        for series in self.series.iter() {
+            let last = series.values.len().saturating_sub(1).max(1) as i32;
+            let points: Vec<Point> = series
+                .values
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| {
+                    let x = plot.top_left.x + (right - plot.top_left.x) * idx as i32 / last;
+                    Point::new(x, self.y(*value, range, &plot))
+                })
+                .collect();
+            match points.as_slice() {
+                [point] => self.draw_points(target, core::iter::once(*point))?,
+                points => self.draw_points(target, self.line(points, series.stroke))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// the smallest rectangle containing both
+pub fn envelope(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    let top_left = a.top_left.component_min(b.top_left);
+    let bottom_right = (a.top_left + a.size).component_max(b.top_left + b.size);
+    let size = bottom_right - top_left;
+    Rectangle::new(top_left, Size::new(size.x as u32, size.y as u32))
+}
+
+// width of the rendered text, every character of a mono font is equally wide
+pub fn text_width(text: &str, font: &MonoFont) -> u32 {
+    match text.chars().count() as u32 {
+        0 => 0,
+        chars => chars * font.character_size.width + (chars - 1) * font.character_spacing,
+    }
+}
+
+// one pixel of padding on the aligned side, centered text may use the whole width
+fn available_width(bounds: &Rectangle, alignment: Alignment) -> u32 {
+    match alignment {
+        Alignment::Center => bounds.size.width,
+        _ => bounds.size.width.saturating_sub(1),
+    }
+}
+
+// where text of the given width starts
+pub fn left_x(bounds: &Rectangle, width: u32, alignment: Alignment) -> i32 {
+    let space = available_width(bounds, alignment).saturating_sub(width) as i32;
+    match alignment {
+        Alignment::Left => bounds.top_left.x + 1,
+        Alignment::Center => bounds.top_left.x + space / 2,
+        Alignment::Right => bounds.top_left.x + space,
+    }
+}
+
+// the first font the text fits with, fonts go from large to small. When it does not fit with
+// any the text is cut short with the smallest font
+pub fn fit(
+    text: &str,
+    width: u32,
+    fonts: &[&'static MonoFont<'static>],
+) -> (&'static MonoFont<'static>, String) {
+    for font in fonts {
+        if text_width(text, font) <= width {
+            return (font, text.to_string());
+        }
+    }
+    let font = fonts[fonts.len() - 1];
+    let mut shortened = text.to_string();
+    while !shortened.is_empty() {
+        shortened.pop();
+        let candidate = format!("{}{}", shortened, ELLIPSIS);
+        if text_width(&candidate, font) <= width {
+            return (font, candidate);
+        }
+    }
+    (font, String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+
+    fn bounds(width: u32) -> Rectangle {
+        Rectangle::new(Point::new(10, 0), Size::new(width, 10))
+    }
+
+    #[test]
+    fn text_width_counts_spacing() {
+        assert_eq!(text_width("", &FONT_6X10), 0);
+        assert_eq!(text_width("a", &FONT_6X10), 6);
+        assert_eq!(text_width("abc", &FONT_6X10), 18);
+        assert_eq!(
+            text_width("abc", &FONT_10X20),
+            30 + 2 * FONT_10X20.character_spacing
+        );
+    }
+
+    #[test]
+    fn left_x_keeps_padding_on_the_aligned_side() {
+        assert_eq!(left_x(&bounds(30), 12, Alignment::Left), 11);
+        assert_eq!(left_x(&bounds(30), 12, Alignment::Right), 10 + 29 - 12);
+        assert_eq!(left_x(&bounds(30), 12, Alignment::Center), 10 + 9);
+        // wider than the bounds starts at the left edge
+        assert_eq!(left_x(&bounds(10), 40, Alignment::Right), 10);
+        assert_eq!(left_x(&bounds(10), 40, Alignment::Center), 10);
+    }
+
+    #[test]
+    fn fit_prefers_the_large_font() {
+        let fonts = [&FONT_6X10, &FONT_4X6];
+        assert_eq!(fit("12.34", 30, &fonts), (&FONT_6X10, "12.34".to_string()));
+        assert_eq!(fit("", 0, &fonts), (&FONT_6X10, String::new()));
+    }
+
+    #[test]
+    fn fit_shrinks_then_shortens() {
+        let fonts = [&FONT_6X10, &FONT_4X6];
+        // 30 pixels with the large font, 23 with the small one
+        assert_eq!(
+            fit("12:05PM", 30, &fonts),
+            (&FONT_4X6, "12:05PM".to_string())
+        );
+        let (font, text) = fit("Equalization charge", 30, &fonts);
+        assert_eq!(font, &FONT_4X6);
+        assert!(text.ends_with(ELLIPSIS));
+        assert!(text_width(&text, font) <= 30);
+        assert_eq!(text, "Equal..");
+        // not even the ellipsis fits
+        assert_eq!(fit("abc", 2, &fonts), (&FONT_4X6, String::new()));
+    }
+
+    #[test]
+    fn label_uses_the_available_width() {
+        let label = Label::new(bounds(19), &FONT_6X10).with_text("abc");
+        assert_eq!(label.fitted(), (&FONT_6X10, "abc".to_string()));
+        let label = Label::new(bounds(18), &FONT_6X10).with_text("abc");
+        assert_eq!(label.fitted(), (&FONT_6X10, "..".to_string()));
+        let label = Label::new(bounds(18), &FONT_6X10)
+            .aligned(Alignment::Center)
+            .with_text("abc");
+        assert_eq!(label.fitted(), (&FONT_6X10, "abc".to_string()));
+    }
+
+    // 9x3, a frame around one dark row
+    static BITMAP: Bitmap = Bitmap::new(9, &[0xff, 0x80, 0x80, 0x80, 0xff, 0x80]);
+
+    #[test]
+    fn icons_shrink_with_small_panels() {
+        assert_eq!(Layout::new(Size::new(296, 128)).icon_scale(), 1.0);
+        assert_eq!(Layout::new(Size::new(800, 480)).icon_scale(), 2.0);
+        for size in [Size::new(250, 122), Size::new(212, 104)] {
+            let layout = Layout::new(size);
+            assert!(layout.icon_scale() < 1.0);
+            let circle = CircleFrame::new(layout.point(0, 0), layout.length(34), 0, &BITMAP)
+                .icon_scaled(layout.icon_scale());
+            let icon = circle.icon.bounds();
+            assert!(icon.size.width < circle.diameter);
+            assert!(icon.top_left.x > circle.top_left.x);
+        }
+    }
+
+    #[test]
+    fn scaled_icons_sample_the_bitmap() {
+        let icon = Icon::new(Point::zero(), &BITMAP).scaled(2.0);
+        assert_eq!(icon.size(), Size::new(18, 6));
+        let mut display = MockDisplay::new();
+        icon.draw(&mut display).unwrap();
+        assert_eq!(display.affected_area(), icon.bounds());
+        assert_eq!(display.get_pixel(Point::new(17, 1)), Some(BinaryColor::On));
+        assert_eq!(display.get_pixel(Point::new(8, 2)), Some(BinaryColor::Off));
+
+        let icon = Icon::new(Point::zero(), &BITMAP).scaled(0.5);
+        assert_eq!(icon.size(), Size::new(5, 2));
+        let mut display = MockDisplay::new();
+        icon.draw(&mut display).unwrap();
+        assert_eq!(display.affected_area(), icon.bounds());
+    }
+
+    fn sparkline(pixel: u32) -> Sparkline {
+        Sparkline::new(
+            Rectangle::new(Point::new(2, 3), Size::new(60, 40)),
+            &FONT_4X6,
+            "kW",
+        )
+        .pixel(pixel)
+        .with_series("pv", Stroke::Solid)
+        .with_series("house", Stroke::Dotted)
+    }
+
+    #[test]
+    fn sparkline_range_includes_zero() {
+        let mut chart = sparkline(1);
+        assert_eq!(chart.range(), (0.0, 1.0));
+        // a flat line at zero still gets an axis
+        chart.set_values(0, [0.0, 0.0, 0.0]);
+        assert_eq!(chart.range(), (0.0, 1.0));
+        chart.set_values(0, [1.0, 3.0, f32::NAN, 2.0]);
+        assert_eq!(chart.range(), (0.0, 3.0));
+        chart.set_values(1, [-2.0, 0.5]);
+        assert_eq!(chart.range(), (-2.0, 3.0));
+        chart.set_values(0, []);
+        chart.set_values(1, [-2.0, -2.0]);
+        assert_eq!(chart.range(), (-2.0, 0.0));
+    }
+
+    #[test]
+    fn sparkline_draws_inside_its_bounds() {
+        for pixel in [1, 2] {
+            let mut chart = sparkline(pixel);
+            chart.set_title("6h");
+            chart.set_values(0, [0.0, 4.0, 1.0]);
+            chart.set_values(1, [2.0, 2.0]);
+            let mut display = MockDisplay::new();
+            display.set_allow_overdraw(true);
+            chart.draw(&mut display).unwrap();
+            assert!(chart.bounds().contains(display.affected_area().top_left));
+            assert!(chart
+                .bounds()
+                .contains(display.affected_area().bottom_right().unwrap()));
+            // the peak touches the top of the plot and the zero line sits at its bottom
+            let plot = chart.plot();
+            let peak = Point::new(plot.center().x, plot.top_left.y);
+            assert_eq!(display.get_pixel(peak), Some(BinaryColor::On));
+            let zero = plot.top_left.y + (plot.size.height - pixel) as i32;
+            assert_eq!(
+                display.get_pixel(Point::new(plot.top_left.x, zero)),
+                Some(BinaryColor::On)
+            );
+        }
+    }
+}
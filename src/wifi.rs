@@ -4,13 +4,14 @@ use esp_idf_svc::nvs::EspDefaultNvsPartition;
 use esp_idf_svc::wifi::{ClientConfiguration, Configuration, EspWifi};
 
 use esp_idf_svc::wifi::BlockingWifi;
+// the nvs partition can only be taken once, so it is passed in and shared with the storage
 pub fn connect_to_wifi<'a>(
     modem: Modem,
+    nvs: EspDefaultNvsPartition,
     ssid: &'a str,
     pass: &'a str,
 ) -> anyhow::Result<BlockingWifi<EspWifi<'a>>> {
     let esp_sys_loop = EspSystemEventLoop::take()?;
-    let nvs = EspDefaultNvsPartition::take()?;
 
     let esp_wifi = EspWifi::new(modem, esp_sys_loop.clone(), Some(nvs))?;
     let mut blocking_wifi = BlockingWifi::wrap(esp_wifi, esp_sys_loop)?;